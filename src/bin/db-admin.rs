@@ -0,0 +1,102 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use rocketlaunch_bot::{
+    config::StorageBackend,
+    db::{self, Dump},
+};
+
+#[derive(Debug, Clone, Parser)]
+struct Args {
+    /// db path
+    db: PathBuf,
+
+    /// storage backend
+    #[arg(long, value_enum, default_value_t = StorageBackend::Sled)]
+    storage: StorageBackend,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+    /// print subscribers with their notified launches
+    List,
+
+    /// export full db contents as json
+    Export {
+        /// output file, stdout if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// import json export into a fresh db
+    Import {
+        /// file produced by `export`
+        input: PathBuf,
+    },
+
+    /// check db for corrupt records
+    Validate,
+}
+
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let db = db::open(args.storage, args.db)?;
+
+    match args.command {
+        Command::List => {
+            for sub in db.subscribers()? {
+                println!("{}: {:?}", sub, db.get_notified(sub)?);
+            }
+        }
+        Command::Export { output } => {
+            let dump = db.export()?;
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            let mut writer = BufWriter::new(writer);
+            serde_json::to_writer_pretty(&mut writer, &dump)?;
+            writeln!(writer)?;
+            eprintln!(
+                "exported {} subscribers, {} launches",
+                dump.subscribers.len(),
+                dump.launches.len()
+            );
+        }
+        Command::Import { input } => {
+            if db.subscribers_count()? > 0 || !db.all_settings()?.is_empty() {
+                eprintln!("refusing to import into non-empty db");
+                return Ok(ExitCode::FAILURE);
+            }
+            let dump: Dump = serde_json::from_reader(BufReader::new(File::open(input)?))?;
+            db.import(&dump)?;
+            eprintln!(
+                "imported {} subscribers, {} launches",
+                dump.subscribers.len(),
+                dump.launches.len()
+            );
+        }
+        Command::Validate => {
+            let problems = db.validate()?;
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            if !problems.is_empty() {
+                eprintln!("found {} corrupt records", problems.len());
+                return Ok(ExitCode::FAILURE);
+            }
+            eprintln!("ok");
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
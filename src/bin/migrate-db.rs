@@ -0,0 +1,35 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use rocketlaunch_bot::db::{DbResult, SledStorage, SqliteStorage, Storage};
+
+/// Copies all data from a sled database into a sqlite one
+#[derive(Debug, Clone, Parser)]
+struct Args {
+    /// source sled db path
+    sled: PathBuf,
+
+    /// target sqlite db path
+    sqlite: PathBuf,
+}
+
+fn main() -> DbResult<ExitCode> {
+    let args = Args::parse();
+
+    let source = SledStorage::open(args.sled)?;
+    let target = SqliteStorage::open(args.sqlite)?;
+    if target.subscribers_count()? > 0 || !target.all_settings()?.is_empty() {
+        eprintln!("refusing to migrate into non-empty db");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let dump = source.export()?;
+    target.import(&dump)?;
+    println!(
+        "migrated {} subscribers, {} launches",
+        dump.subscribers.len(),
+        dump.launches.len()
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -1,11 +1,22 @@
+use std::path::PathBuf;
+
 use clap::Parser;
-use rocketlaunch_bot::{bot::init_bot, config::BotConfig, db::Db};
+use rocketlaunch_bot::{
+    bot::init_bot,
+    config::{BotConfig, StorageBackend},
+    db,
+};
+use tokio::sync::mpsc;
 
 #[derive(Debug, Clone, Parser)]
 struct Args {
     /// bot token
     #[clap(flatten)]
     bot: BotConfig,
+
+    /// db path
+    #[arg(long, default_value = "test_db")]
+    db_path: PathBuf,
 }
 
 #[tokio::main]
@@ -13,8 +24,10 @@ async fn main() {
     let args = Args::parse();
     dbg!(&args);
 
-    let db = Db::open("test_db").unwrap();
+    let db = db::open(StorageBackend::Sled, &args.db_path).unwrap();
 
-    let (_bot, mut dispatcher) = init_bot(args.bot, db).await;
+    // there's no worker to serve refresh requests
+    let (refresh_tx, _) = mpsc::channel(1);
+    let (_bot, mut dispatcher) = init_bot(args.bot, db, refresh_tx).await;
     dispatcher.dispatch().await;
 }
@@ -1,38 +1,80 @@
-use std::fmt::Write;
+use std::fmt::{Debug, Write};
 
-use chrono::{Duration, DurationRound, TimeDelta, Utc};
-use humantime::format_duration;
+use chrono::{DateTime, Duration, DurationRound, TimeDelta, Utc};
 use teloxide::{
     adaptors::{trace, CacheMe, DefaultParseMode, Throttle, Trace},
     dispatching::{Dispatcher, UpdateFilterExt},
     prelude::*,
     requests::ResponseResult,
-    types::{Message, MessageId, ParseMode, Update},
+    types::{
+        CallbackQuery, Chat, InlineKeyboardMarkup, Message, MessageKind, ParseMode, Update, User,
+    },
     utils::{command::BotCommands, markdown},
     ApiError, Bot, RequestError,
 };
-use tracing::{info, warn};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
 
+use self::{
+    admin::{admin_callback_handler, command_handler, AdminCommand},
+    digest::{digest_command, digest_text},
+    inline::inline_query_handler,
+    launch::{follow_button, follow_command, launch_callback_handler, launch_command},
+    pin::{pin_command, remove_pin, update_pin},
+    quiet::{quiet_hours_command, reminder_sound_command, timezone_command},
+    search::find_text,
+    settings::{can_manage, is_chat_admin, settings_text},
+    tags::{tag_command, tags_text},
+    vehicles::vehicle_command,
+};
 use crate::{
     config::BotConfig,
-    db::Db,
+    db::{ChatSettings, ChatType, Db, DbResult, Event, QuietMode, Subscriber, SubscriberInfo},
+    fetch::RefreshRequest,
+    i18n::Lang,
+    template::{Field, Template},
     types::{Launch, RLError},
 };
 
+mod admin;
+mod digest;
+mod inline;
+mod launch;
+mod pin;
+mod quiet;
+mod search;
+mod settings;
+mod tags;
+mod vehicles;
+
+/// How late a digest may still be sent.
+const DIGEST_LATENESS: TimeDelta = TimeDelta::hours(1);
+
 pub type MyBot = Trace<Throttle<CacheMe<DefaultParseMode<Bot>>>>;
 pub type MyDispatcher =
     Dispatcher<MyBot, teloxide::RequestError, teloxide::dispatching::DefaultKey>;
 
-pub async fn init_bot(config: BotConfig, db: Db) -> (MyBot, MyDispatcher) {
+pub async fn init_bot(
+    config: BotConfig,
+    db: Db,
+    refresh: mpsc::Sender<RefreshRequest>,
+) -> (MyBot, MyDispatcher) {
     let bot: MyBot = Bot::new(config.token.clone())
         .parse_mode(ParseMode::MarkdownV2)
         .cache_me()
         .throttle(Default::default())
         .trace(trace::Settings::TRACE_EVERYTHING);
 
-    let handler = Update::filter_message()
+    db.seed_admins(&config.admin_chats)
+        .expect("failed seeding admins");
+    if let Some(template) = &config.notification_template {
+        db.set_notification_template(Some(template.source()))
+            .expect("failed saving notification template");
+    }
+
+    let messages_handler = Update::filter_message()
         .branch(
-            dptree::filter(|cfg: BotConfig, msg: Message| cfg.admin_chats.contains(&msg.chat.id.0))
+            dptree::filter(|db: Db, msg: Message| db.is_admin(msg.chat.id.0).unwrap_or(false))
                 .branch(
                     dptree::entry()
                         .filter_command::<AdminCommand>()
@@ -45,10 +87,41 @@ pub async fn init_bot(config: BotConfig, db: Db) -> (MyBot, MyDispatcher) {
                 .endpoint(unauthorized_command_handler),
         );
 
+    let channel_posts_handler = Update::filter_channel_post().branch(
+        dptree::entry()
+            .filter_command::<UnauthorizedCommand>()
+            .endpoint(unauthorized_command_handler),
+    );
+
+    let callbacks_handler = Update::filter_callback_query()
+        .branch(
+            dptree::filter(|db: Db, q: CallbackQuery| {
+                let is_admin_callback = q
+                    .data
+                    .as_deref()
+                    .and_then(Callback::decode)
+                    .is_some_and(|callback| callback.is_admin());
+                is_admin_callback
+                    && q.message
+                        .as_ref()
+                        .is_some_and(|msg| db.is_admin(msg.chat.id.0).unwrap_or(false))
+            })
+            .endpoint(admin_callback_handler),
+        )
+        .branch(dptree::endpoint(launch_callback_handler));
+
+    let inline_queries_handler = Update::filter_inline_query().endpoint(inline_query_handler);
+
+    let handler = dptree::entry()
+        .branch(messages_handler)
+        .branch(channel_posts_handler)
+        .branch(callbacks_handler)
+        .branch(inline_queries_handler);
+
     (
         bot.clone(),
         Dispatcher::builder(bot.clone(), handler)
-            .dependencies(dptree::deps![config, db])
+            .dependencies(dptree::deps![config, db, refresh])
             .build(),
     )
 }
@@ -59,7 +132,7 @@ enum UnauthorizedCommand {
     #[command(description = "current chat id")]
     Id,
 
-    #[command(description = "subscribe to launches notifications")]
+    #[command(description = "subscribe to launches notifications, in forums per topic")]
     Start,
 
     #[command(description = "unsubscribe from launches notifications")]
@@ -71,18 +144,146 @@ enum UnauthorizedCommand {
     #[command(description = "show next launch")]
     Next,
 
+    #[command(description = "launch details by id or slug, next launch by default")]
+    Launch(String),
+
+    #[command(description = "get notified about a single launch by id or slug")]
+    Follow(String),
+
+    #[command(description = "stop following a launch")]
+    Unfollow(String),
+
+    #[command(description = "get notified about every launch of a vehicle, lists vehicles")]
+    FollowVehicle(String),
+
+    #[command(description = "stop following a vehicle")]
+    UnfollowVehicle(String),
+
+    #[command(description = "list tags of cached launches")]
+    Tags,
+
+    #[command(description = "follow, unfollow, mute or unmute launches by tag")]
+    Tag(String),
+
+    #[command(
+        description = "find launches, e.g. /find provider:spacex country:us tag:crew next:7d"
+    )]
+    Find(String),
+
+    #[command(description = "show chat settings")]
+    Settings,
+
+    #[command(description = "include or exclude suborbital launches")]
+    Suborbital(String),
+
+    #[command(
+        description = "make reminders at a lead time loud or silent, e.g. /reminder_sound 24h silent"
+    )]
+    ReminderSound(String),
+
+    #[command(description = "timezone of the chat, e.g. Europe/Berlin")]
+    Timezone(String),
+
+    #[command(
+        description = "quiet hours, e.g. /quiet_hours 23:00-07:00 delay|silent|drop, or off"
+    )]
+    QuietHours(String),
+
+    #[command(
+        description = "launch digest instead of notifications, e.g. /digest daily 08:00, or off"
+    )]
+    Digest(String),
+
+    #[command(description = "keep the next launch pinned: on or off")]
+    PinNext(String),
+
+    #[command(description = "language of the bot: en or ru")]
+    Language(String),
+
+    #[command(description = "who can manage group subscription: admins or everyone")]
+    GroupControl(String),
+
     #[command(description = "help")]
     Help,
 }
 
-#[derive(BotCommands, Clone, Debug)]
-#[command(rename_rule = "snake_case", description = "Admin commands:")]
-enum AdminCommand {
-    #[command(description = "help")]
-    Help,
+impl UnauthorizedCommand {
+    /// Commands changing chat subscription or settings.
+    fn changes_state(&self) -> bool {
+        matches!(
+            self,
+            UnauthorizedCommand::Start
+                | UnauthorizedCommand::Stop
+                | UnauthorizedCommand::Follow(_)
+                | UnauthorizedCommand::Unfollow(_)
+                | UnauthorizedCommand::FollowVehicle(_)
+                | UnauthorizedCommand::UnfollowVehicle(_)
+                | UnauthorizedCommand::Tag(_)
+                | UnauthorizedCommand::Suborbital(_)
+                | UnauthorizedCommand::ReminderSound(_)
+                | UnauthorizedCommand::Timezone(_)
+                | UnauthorizedCommand::QuietHours(_)
+                | UnauthorizedCommand::Digest(_)
+                | UnauthorizedCommand::PinNext(_)
+                | UnauthorizedCommand::Language(_)
+                | UnauthorizedCommand::GroupControl(_)
+        )
+    }
+}
 
-    #[command(description = "subscribers count")]
-    SubscribersCount,
+/// Payload of inline keyboard buttons.
+#[derive(Debug, Clone, PartialEq)]
+enum Callback {
+    SubscribersPage(usize),
+    /// Confirms broadcasting of given message from admin chat.
+    BroadcastSend(i32),
+    BroadcastCancel(i32),
+    /// Shows launch details in place of the message.
+    LaunchShow(u64),
+    LaunchMute(u64),
+    LaunchUnmute(u64),
+    LaunchFollow(u64),
+    LaunchUnfollow(u64),
+}
+
+impl Callback {
+    fn encode(&self) -> String {
+        match self {
+            Callback::SubscribersPage(page) => format!("subscribers:{}", page),
+            Callback::BroadcastSend(msg_id) => format!("broadcast:{}", msg_id),
+            Callback::BroadcastCancel(msg_id) => format!("broadcast_cancel:{}", msg_id),
+            Callback::LaunchShow(id) => format!("launch:{}", id),
+            Callback::LaunchMute(id) => format!("mute:{}", id),
+            Callback::LaunchUnmute(id) => format!("unmute:{}", id),
+            Callback::LaunchFollow(id) => format!("follow:{}", id),
+            Callback::LaunchUnfollow(id) => format!("unfollow:{}", id),
+        }
+    }
+
+    fn decode(data: &str) -> Option<Self> {
+        let (kind, arg) = data.split_once(':')?;
+        match kind {
+            "subscribers" => Some(Callback::SubscribersPage(arg.parse().ok()?)),
+            "broadcast" => Some(Callback::BroadcastSend(arg.parse().ok()?)),
+            "broadcast_cancel" => Some(Callback::BroadcastCancel(arg.parse().ok()?)),
+            "launch" => Some(Callback::LaunchShow(arg.parse().ok()?)),
+            "mute" => Some(Callback::LaunchMute(arg.parse().ok()?)),
+            "unmute" => Some(Callback::LaunchUnmute(arg.parse().ok()?)),
+            "follow" => Some(Callback::LaunchFollow(arg.parse().ok()?)),
+            "unfollow" => Some(Callback::LaunchUnfollow(arg.parse().ok()?)),
+            _ => None,
+        }
+    }
+
+    /// Buttons only sent to admin chats.
+    fn is_admin(&self) -> bool {
+        matches!(
+            self,
+            Callback::SubscribersPage(_)
+                | Callback::BroadcastSend(_)
+                | Callback::BroadcastCancel(_)
+        )
+    }
 }
 
 #[tracing::instrument(skip_all)]
@@ -93,157 +294,521 @@ async fn unauthorized_command_handler(
     db: Db,
 ) -> ResponseResult<()> {
     info!("handling: {:?}", cmd);
+    let sub = msg_subscriber(&msg);
+    let lang = msg_lang(&db, &msg);
+    let tr = lang.catalog();
+    if cmd.changes_state() && !can_manage(&bot, &db, &msg).await? {
+        reply(&bot, &msg, markdown::escape(tr.admins_only)).await?;
+        return Ok(());
+    }
     match cmd {
         UnauthorizedCommand::Id => {
-            bot.send_message(msg.chat.id, format!("`{}`", msg.chat.id))
-                .reply_to_message_id(msg.id)
-                .await?;
+            let text = match sub.thread_id {
+                Some(thread_id) => format!("`{}`\n{} `{}`", msg.chat.id, tr.topic, thread_id),
+                None => format!("`{}`", msg.chat.id),
+            };
+            reply(&bot, &msg, text).await?;
         }
         UnauthorizedCommand::Help => {
-            bot.send_message(msg.chat.id, UnauthorizedCommand::descriptions().to_string())
-                .reply_to_message_id(msg.id)
-                .await?;
+            reply(&bot, &msg, markdown::escape(&help_text(lang))).await?;
         }
-        UnauthorizedCommand::Start => match db.subscribe(msg.chat.id.0) {
+        UnauthorizedCommand::Start => match subscribe(&db, sub, &msg.chat) {
             Ok(_) => {
-                bot.send_message(
-                    msg.chat.id,
-                    markdown::escape("Subscribed, standby for notifications!"),
-                )
-                .reply_to_message_id(msg.id)
-                .await?;
+                // language of whoever subscribed is the one of notifications
+                let _ = update_settings(&db, sub, |settings| {
+                    settings.language.get_or_insert(lang);
+                });
+                reply(&bot, &msg, markdown::escape(tr.subscribed)).await?;
                 let notify_up_to = Utc::now() + Duration::try_days(2).unwrap();
-                for launch in db.get_launches().unwrap_or_default() {
+                let settings = db.get_settings(sub).unwrap_or_default();
+                // digest chats hear about upcoming launches in the next digest
+                let launches = match settings.digest {
+                    Some(_) => vec![],
+                    None => db.get_launches().unwrap_or_default(),
+                };
+                for launch in launches {
                     let Some(t0) = launch.t0 else {
                         continue;
                     };
-                    if t0 > notify_up_to {
+                    if t0 > notify_up_to
+                        || settings.filters_out(&launch)
+                        || settings.muted_launches.contains(&launch.id)
+                    {
                         continue;
                     }
-                    let _ = launch_notify(&bot, &db, &launch, msg.chat.id.0, None).await;
+                    let _ = launch_notify(&bot, &db, &launch, sub, None, false).await;
                 }
             }
             Err(err) => {
-                bot.send_message(
-                    msg.chat.id,
-                    format!(
-                        "Error subscribing:\n```\n{}\n```",
-                        markdown::escape(&format!("{:?}", err))
-                    ),
-                )
-                .reply_to_message_id(msg.id)
-                .await?;
+                reply(&bot, &msg, error_text(tr.error_subscribing, err)).await?;
             }
         },
-        UnauthorizedCommand::Stop => match db.unsubscribe(msg.chat.id.0) {
+        UnauthorizedCommand::Stop => match remove_pin(&bot, &db, sub)
+            .await
+            .and_then(|_| unsubscribe(&db, sub))
+        {
             Ok(_) => {
-                bot.send_message(msg.chat.id, "Unsubscribed")
-                    .reply_to_message_id(msg.id)
-                    .await?;
+                reply(&bot, &msg, markdown::escape(tr.unsubscribed)).await?;
             }
             Err(err) => {
-                bot.send_message(
-                    msg.chat.id,
-                    format!(
-                        "Error unsubscribing:\n```\n{}\n```",
-                        markdown::escape(&format!("{:?}", err))
-                    ),
-                )
-                .reply_to_message_id(msg.id)
-                .await?;
+                reply(&bot, &msg, error_text(tr.error_unsubscribing, err)).await?;
             }
         },
         UnauthorizedCommand::Launches => {
+            let settings = db.get_settings(sub).unwrap_or_default();
             for launch in db.get_launches().unwrap_or_default() {
-                if launch.t0.is_none() {
+                if launch.t0.is_none() || !settings.shows(&launch) {
                     continue;
                 }
-                let _ = launch_notify(&bot, &db, &launch, msg.chat.id.0, Some(msg.id)).await;
+                let _ = launch_notify(&bot, &db, &launch, sub, Some(&msg), false).await;
             }
         }
         UnauthorizedCommand::Next => {
             let now = Utc::now();
+            let settings = db.get_settings(sub).unwrap_or_default();
             if let Some(launch) = db
                 .get_launches()
                 .unwrap_or_default()
                 .iter()
                 .filter(|l| {
                     if let Some(t0) = l.t0 {
-                        t0 >= now
+                        t0 >= now && settings.shows(l)
                     } else {
                         false
                     }
                 })
                 .min_by_key(|l| l.t0)
             {
-                let _ = launch_notify(&bot, &db, launch, msg.chat.id.0, Some(msg.id)).await;
+                let _ = launch_notify(&bot, &db, launch, sub, Some(&msg), false).await;
             }
         }
+        UnauthorizedCommand::Launch(query) => {
+            launch_command(&bot, &db, &msg, &query).await?;
+        }
+        UnauthorizedCommand::Follow(query) => {
+            follow_command(&bot, &db, &msg, &query, true).await?;
+        }
+        UnauthorizedCommand::Unfollow(query) => {
+            follow_command(&bot, &db, &msg, &query, false).await?;
+        }
+        UnauthorizedCommand::FollowVehicle(query) => {
+            vehicle_command(&bot, &db, &msg, &query, true).await?;
+        }
+        UnauthorizedCommand::UnfollowVehicle(query) => {
+            vehicle_command(&bot, &db, &msg, &query, false).await?;
+        }
+        UnauthorizedCommand::Tags => {
+            reply(
+                &bot,
+                &msg,
+                tags_text(&db.get_launches().unwrap_or_default(), lang),
+            )
+            .await?;
+        }
+        UnauthorizedCommand::Tag(args) => {
+            tag_command(&bot, &db, &msg, &args).await?;
+        }
+        UnauthorizedCommand::Find(query) => {
+            let text = find_text(&db.get_launches().unwrap_or_default(), &query, lang);
+            reply(&bot, &msg, text)
+                .disable_web_page_preview(true)
+                .await?;
+        }
+        UnauthorizedCommand::Settings => {
+            let text = settings_text(&db, &msg, lang)
+                .unwrap_or_else(|err| error_text(tr.error_getting_settings, err));
+            reply(&bot, &msg, text).await?;
+        }
+        UnauthorizedCommand::Suborbital(choice) => {
+            let exclude = match choice.trim() {
+                "include" => false,
+                "exclude" => true,
+                _ => {
+                    let usage = format!(
+                        "{}: `/suborbital include|exclude`",
+                        markdown::escape(tr.usage)
+                    );
+                    reply(&bot, &msg, usage).await?;
+                    return Ok(());
+                }
+            };
+            let text =
+                match update_settings(&db, sub, |settings| settings.exclude_suborbital = exclude) {
+                    Ok(()) if exclude => markdown::escape(tr.suborbital_excluded),
+                    Ok(()) => markdown::escape(tr.suborbital_included),
+                    Err(err) => error_text(tr.error_saving_settings, err),
+                };
+            reply(&bot, &msg, text).await?;
+        }
+        UnauthorizedCommand::ReminderSound(args) => {
+            reminder_sound_command(&bot, &db, &msg, &args).await?;
+        }
+        UnauthorizedCommand::Timezone(name) => {
+            timezone_command(&bot, &db, &msg, &name).await?;
+        }
+        UnauthorizedCommand::QuietHours(args) => {
+            quiet_hours_command(&bot, &db, &msg, &args).await?;
+        }
+        UnauthorizedCommand::Digest(args) => {
+            digest_command(&bot, &db, &msg, &args).await?;
+        }
+        UnauthorizedCommand::PinNext(args) => {
+            pin_command(&bot, &db, &msg, &args).await?;
+        }
+        UnauthorizedCommand::Language(code) => {
+            let text = match Lang::parse(code.trim()) {
+                Some(lang) => {
+                    match update_settings(&db, sub, |settings| settings.language = Some(lang)) {
+                        Ok(()) => markdown::escape(lang.catalog().language_set),
+                        Err(err) => error_text(lang.catalog().error_saving_settings, err),
+                    }
+                }
+                None => {
+                    let langs = Lang::ALL
+                        .iter()
+                        .map(|lang| {
+                            format!(
+                                "`{}` {}",
+                                lang.as_str(),
+                                markdown::escape(lang.catalog().name)
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    format!(
+                        "{}: `/language <code>`\n\n{}",
+                        markdown::escape(tr.usage),
+                        langs
+                    )
+                }
+            };
+            reply(&bot, &msg, text).await?;
+        }
+        UnauthorizedCommand::GroupControl(who) => {
+            let everyone = match who.trim() {
+                "everyone" => true,
+                "admins" => false,
+                _ => {
+                    let usage = format!(
+                        "{}: `/group_control admins|everyone`",
+                        markdown::escape(tr.usage)
+                    );
+                    reply(&bot, &msg, usage).await?;
+                    return Ok(());
+                }
+            };
+            // group administrators only, even if everyone can manage the chat
+            let text = if msg.chat.is_private() {
+                markdown::escape(tr.groups_only)
+            } else if !is_chat_admin(&bot, &msg).await? {
+                markdown::escape(tr.admins_only)
+            } else {
+                match update_settings(&db, Subscriber::chat(msg.chat.id.0), |settings| {
+                    settings.everyone_can_manage = everyone
+                }) {
+                    Ok(()) if everyone => markdown::escape(tr.everyone_manages),
+                    Ok(()) => markdown::escape(tr.admins_manage),
+                    Err(err) => error_text(tr.error_saving_settings, err),
+                }
+            };
+            reply(&bot, &msg, text).await?;
+        }
     }
     Ok(())
 }
 
-#[tracing::instrument(skip_all)]
-async fn command_handler(
-    bot: MyBot,
-    msg: Message,
-    cmd: AdminCommand,
-    db: Db,
-) -> ResponseResult<()> {
-    info!("handling: {:?}", cmd);
-    match cmd {
-        AdminCommand::Help => {
-            let commands = [
-                AdminCommand::descriptions().to_string(),
-                UnauthorizedCommand::descriptions().to_string(),
-            ];
-            bot.send_message(msg.chat.id, markdown::escape(&commands.join("\n\n")))
-                .reply_to_message_id(msg.id)
-                .await?;
-        }
-        AdminCommand::SubscribersCount => {
-            let count = db
-                .subscribers_count()
-                .expect("failed getting subscribers count");
-            bot.send_message(msg.chat.id, format!("Total subscribers: {}", count))
-                .reply_to_message_id(msg.id)
-                .await?;
+/// Language to answer `msg` in: the one chosen for the chat, or the one of
+/// sender's Telegram app.
+fn msg_lang(db: &Db, msg: &Message) -> Lang {
+    user_lang(db, msg_subscriber(msg), msg.from())
+}
+
+/// Language to answer `user` in `sub`, the same way as [`msg_lang`].
+fn user_lang(db: &Db, sub: Subscriber, user: Option<&User>) -> Lang {
+    db.get_settings(sub)
+        .ok()
+        .and_then(|settings| settings.language)
+        .or_else(|| {
+            user.and_then(|user| user.language_code.as_deref())
+                .and_then(Lang::from_code)
+        })
+        .unwrap_or_default()
+}
+
+/// Command descriptions in `lang`, English for the ones not translated.
+fn help_text(lang: Lang) -> String {
+    let tr = lang.catalog();
+    let mut text = tr.commands.to_string();
+    for command in UnauthorizedCommand::bot_commands() {
+        let name = command.command.trim_start_matches('/');
+        let description = tr
+            .command_descriptions
+            .iter()
+            .find(|(command, _)| *command == name)
+            .map_or(command.description.as_str(), |(_, description)| description);
+        let _ = write!(text, "\n/{} — {}", name, description);
+    }
+    text
+}
+
+/// Subscription `msg` is about: the forum topic it's posted in, or the whole
+/// chat. Reply threads of non-forum groups don't count as topics.
+fn msg_subscriber(msg: &Message) -> Subscriber {
+    let is_topic = match &msg.kind {
+        MessageKind::Common(common) => common.is_topic_message,
+        _ => false,
+    };
+    Subscriber {
+        chat_id: msg.chat.id.0,
+        thread_id: msg.thread_id.filter(|_| is_topic),
+    }
+}
+
+/// Starts a message to `sub`, posting into its forum topic if it has one.
+fn send_to(
+    bot: &MyBot,
+    sub: Subscriber,
+    text: impl Into<String>,
+) -> <MyBot as Requester>::SendMessage {
+    let request = bot.send_message(ChatId(sub.chat_id), text);
+    match sub.thread_id {
+        Some(thread_id) => request.message_thread_id(thread_id),
+        None => request,
+    }
+}
+
+/// Starts a reply to `msg`, channel posts are answered without reply-to.
+fn reply(bot: &MyBot, msg: &Message, text: impl Into<String>) -> <MyBot as Requester>::SendMessage {
+    let request = send_to(bot, msg_subscriber(msg), text);
+    if msg.chat.is_channel() {
+        request
+    } else {
+        request.reply_to_message_id(msg.id)
+    }
+}
+
+/// Catalog `text` escaped for MarkdownV2, with its `{}`s replaced by `args`
+/// in order. Arguments are inserted as they are, so they can have markup.
+fn fill(text: &str, args: &[&str]) -> String {
+    let mut filled = String::new();
+    for (i, part) in text.split("{}").enumerate() {
+        if i > 0 {
+            filled.push_str(args.get(i - 1).copied().unwrap_or_default());
         }
+        filled.push_str(&markdown::escape(part));
+    }
+    filled
+}
+
+fn error_text(what: &str, err: impl Debug) -> String {
+    format!(
+        "{}:\n```\n{}\n```",
+        markdown::escape(what),
+        markdown::escape(&format!("{:?}", err))
+    )
+}
+
+fn chat_info(chat: &Chat) -> SubscriberInfo {
+    let chat_type = if chat.is_private() {
+        ChatType::Private
+    } else if chat.is_group() {
+        ChatType::Group
+    } else if chat.is_supergroup() {
+        ChatType::Supergroup
+    } else if chat.is_channel() {
+        ChatType::Channel
+    } else {
+        ChatType::Unknown
+    };
+    let title = chat.title().map(ToString::to_string).or_else(|| {
+        let name = [chat.first_name(), chat.last_name()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!name.is_empty()).then_some(name)
+    });
+    SubscriberInfo {
+        chat_type,
+        title,
+        username: chat.username().map(ToString::to_string),
+        ..Default::default()
+    }
+}
+
+/// Subscribes chat or its topic, refreshing stored info of the chat.
+fn subscribe(db: &Db, sub: Subscriber, chat: &Chat) -> DbResult<()> {
+    let was_subscribed = db.is_subscribed(sub)?;
+    db.subscribe(sub)?;
+    let current = chat_info(chat);
+    let mut info = db.get_subscriber_info(sub)?;
+    info.chat_type = current.chat_type;
+    info.title = current.title;
+    info.username = current.username;
+    if !was_subscribed || info.subscribed_at.is_none() {
+        info.subscribed_at = Some(Utc::now());
+    }
+    db.set_subscriber_info(sub, &info)?;
+    if !was_subscribed {
+        db.record_event(&Event::Subscribed {
+            chat_id: sub.chat_id,
+        })?;
+    }
+    Ok(())
+}
+
+fn update_settings(
+    db: &Db,
+    sub: Subscriber,
+    update: impl FnOnce(&mut ChatSettings),
+) -> DbResult<()> {
+    let mut settings = db.get_settings(sub)?;
+    update(&mut settings);
+    db.set_settings(sub, &settings)
+}
+
+fn unsubscribe(db: &Db, sub: Subscriber) -> DbResult<()> {
+    if db.is_subscribed(sub)? {
+        db.unsubscribe(sub)?;
+        db.record_event(&Event::Unsubscribed {
+            chat_id: sub.chat_id,
+        })?;
     }
     Ok(())
 }
 
+fn format_time(t: Option<DateTime<Utc>>) -> String {
+    t.map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "never".to_string())
+}
+
+/// Sends due notifications, holding back those falling into quiet hours of
+/// the chat as it chose. Notifications of launches already gone, like delayed
+/// past T-0, are marked sent without sending.
 pub async fn launches_notify(bot: &MyBot, db: &Db, launches: &[Launch]) -> Result<(), RLError> {
+    let now = Utc::now();
     for launch in launches {
         let Some(t0) = launch.t0 else {
             continue;
         };
-        for chat_id in db.get_unnotified(launch.id, t0)? {
-            launch_notify(bot, db, launch, chat_id, None).await?;
+        for sub in db.get_unnotified(launch)? {
+            if t0 <= now {
+                db.set_notified(sub, launch.id, t0)?;
+                continue;
+            }
+            let quiet = db.get_settings(sub)?.quiet_at(now);
+            match quiet.map(|quiet| quiet.mode) {
+                None => launch_notify(bot, db, launch, sub, None, false).await?,
+                Some(QuietMode::Silent) => launch_notify(bot, db, launch, sub, None, true).await?,
+                Some(QuietMode::Delay) => {
+                    debug!("delaying notification of {} about {}", sub, launch.id);
+                }
+                Some(QuietMode::Drop) => {
+                    info!("dropping notification of {} about {}", sub, launch.id);
+                    db.set_notified(sub, launch.id, t0)?;
+                }
+            }
         }
     }
 
     Ok(())
 }
 
-pub async fn launch_notify(
-    bot: &MyBot,
-    db: &Db,
+/// Sends digests due since the last run. Ones missed by more than
+/// [`DIGEST_LATENESS`], like while the bot was down, wait for the next time.
+/// Failing chats are retried next run, without holding back the others.
+pub async fn digests_notify(bot: &MyBot, db: &Db, launches: &[Launch]) -> Result<(), RLError> {
+    let now = Utc::now();
+    for sub in db.subscribers()? {
+        let settings = db.get_settings(sub)?;
+        let Some(digest) = settings.digest else {
+            continue;
+        };
+        let due = digest.last_due(settings.timezone, now);
+        let mut info = db.get_subscriber_info(sub)?;
+        if info.last_digest.is_some_and(|t| t >= due) || now - due > DIGEST_LATENESS {
+            continue;
+        }
+
+        info!("sending digest to {}", sub);
+        let text = digest_text(launches, &settings, &digest, now);
+        match send_to(bot, sub, text).disable_web_page_preview(true).await {
+            Ok(_) => {
+                info.last_digest = Some(now);
+                db.set_subscriber_info(sub, &info)?;
+                db.record_delivery(sub)?;
+            }
+            Err(err) => {
+                warn!("failed sending digest to {}: {}", sub, err);
+                handle_send_error(db, sub, &err)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Keeps pinned next launch messages up to date. Failing chats are skipped
+/// until the next run, so they don't hold back the others.
+pub async fn pins_update(bot: &MyBot, db: &Db, launches: &[Launch]) -> Result<(), RLError> {
+    for sub in db.subscribers()? {
+        if !db.get_settings(sub)?.pin_next {
+            continue;
+        }
+        if let Err(err) = update_pin(bot, db, sub, launches).await {
+            warn!("failed updating pinned message in {}: {}", sub, err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Notification template admins set, invalid ones are ignored.
+fn notification_template(db: &Db) -> Option<Template> {
+    let source = db.notification_template().ok()??;
+    Template::parse(&source)
+        .inspect_err(|err| warn!("ignoring bad notification template: {}", err))
+        .ok()
+}
+
+/// Notification text for `launch`, as of `now`, in `lang` unless rendered
+/// from `template`.
+pub fn launch_text(
     launch: &Launch,
-    chat_id: i64,
-    msg_id: Option<MessageId>,
-) -> Result<(), RLError> {
-    let now = Utc::now().duration_round(TimeDelta::try_minutes(1).unwrap())?;
-    let Some(t0) = launch.t0 else {
-        return Ok(());
-    };
+    t0: DateTime<Utc>,
+    now: DateTime<Utc>,
+    lang: Lang,
+    template: Option<&Template>,
+) -> Result<String, RLError> {
+    let tr = lang.catalog();
+    let countdown = tr.duration((t0 - now).to_std()?);
+    if let Some(template) = template {
+        return Ok(template.render(|field| match field {
+            Field::Provider => launch.provider.name.clone(),
+            Field::Vehicle => launch.vehicle.name.clone(),
+            Field::Mission => launch.name.clone(),
+            Field::Pad => launch.pad.to_string(),
+            Field::Window => match (launch.win_open, launch.win_close) {
+                (Some(open), Some(close)) if open != close => format!(
+                    "{} – {}",
+                    open.format("%Y-%m-%d %H:%M"),
+                    close.format("%H:%M UTC")
+                ),
+                _ => t0.format("%Y-%m-%d %H:%M UTC").to_string(),
+            },
+            Field::Countdown => countdown.clone(),
+            Field::Link => format!("https://rocketlaunch.live/launch/{}", launch.slug),
+        }));
+    }
+
     let mut text = format!(
-        "[{} \\- {}](https://rocketlaunch.live/launch/{})\n{} \\(in *{}*\\)\n{}",
+        "[{} \\- {}](https://rocketlaunch.live/launch/{})\n{} \\({} *{}*\\)\n{}",
         markdown::escape(&launch.provider.name),
         markdown::escape(&launch.vehicle.name),
         markdown::escape(&launch.slug),
         markdown::escape(&format!("{}", t0)),
-        markdown::escape(&format!("{}", format_duration((t0 - now).to_std()?))),
+        markdown::escape(tr.countdown_in),
+        markdown::escape(&countdown),
         markdown::escape(&format!("{}", launch.pad)),
     );
 
@@ -252,49 +817,121 @@ pub async fn launch_notify(
     }
 
     if launch.suborbital {
-        let _ = write!(text, "\n\nsuborbital");
+        let _ = write!(text, "\n\n{}", markdown::escape(tr.suborbital));
     }
 
-    info!("notifying {} about launch {}", chat_id, launch.id);
-    let mut fut = bot.send_message(ChatId(chat_id), &text);
-    if let Some(msg_id) = msg_id {
-        fut = fut.reply_to_message_id(msg_id);
-    }
+    Ok(text)
+}
+
+/// Sends `launch` to `sub`, or replies to `reply_to` with it. Notifications are
+/// `silent`, or as the lead time they're due to is marked.
+pub async fn launch_notify(
+    bot: &MyBot,
+    db: &Db,
+    launch: &Launch,
+    sub: Subscriber,
+    reply_to: Option<&Message>,
+    silent: bool,
+) -> Result<(), RLError> {
+    let now = Utc::now().duration_round(TimeDelta::try_minutes(1).unwrap())?;
+    let Some(t0) = launch.t0 else {
+        return Ok(());
+    };
+    let settings = db.get_settings(sub)?;
+    let lang = match reply_to {
+        Some(msg) => msg_lang(db, msg),
+        None => settings.language.unwrap_or_default(),
+    };
+    let text = launch_text(launch, t0, now, lang, notification_template(db).as_ref())?;
+
+    info!("notifying {} about launch {}", sub, launch.id);
+    let fut = match reply_to {
+        Some(msg) => {
+            let following = db.get_follows(sub)?.contains_key(&launch.id);
+            reply(bot, msg, &text).reply_markup(InlineKeyboardMarkup::new([[follow_button(
+                launch.id, following, lang,
+            )]]))
+        }
+        None => {
+            let silent = silent || settings.silent_at((t0 - now).num_seconds());
+            send_to(bot, sub, &text).disable_notification(silent)
+        }
+    };
+    let is_notification = reply_to.is_none();
     match fut.await {
         Ok(_) => {
-            db.set_notified(chat_id, launch.id, t0)?;
+            db.set_notified(sub, launch.id, t0)?;
+            db.record_delivery(sub)?;
+            if is_notification {
+                db.record_event(&Event::NotificationSent {
+                    chat_id: sub.chat_id,
+                    launch_id: launch.id,
+                })?;
+            }
         }
         Err(err) => {
-            match err {
-                RequestError::Api(ref api_err) => match api_err {
-                    ApiError::BotBlocked
-                    | ApiError::BotKicked
-                    | ApiError::BotKickedFromSupergroup
-                    | ApiError::ChatNotFound
-                    | ApiError::UserNotFound
-                    | ApiError::UserDeactivated
-                    | ApiError::GroupDeactivated
-                    | ApiError::CantTalkWithBots => {
-                        warn!(
-                            "unsubscribing {} from updates due to api error {}",
-                            chat_id, api_err
-                        );
-                        db.unsubscribe(chat_id)?;
-                    }
-                    _ => {}
-                },
-                RequestError::MigrateToChatId(new_chat_id) => {
-                    warn!(
-                        "chat_id {} migrated to new chat_id {}",
-                        chat_id, new_chat_id
-                    );
-                    db.replace_chat_id(chat_id, new_chat_id)?;
-                }
-                _ => {}
+            if is_notification {
+                db.record_event(&Event::NotificationFailed {
+                    chat_id: sub.chat_id,
+                    launch_id: launch.id,
+                })?;
             }
+            handle_send_error(db, sub, &err)?;
             Err(err)?;
         }
     }
 
     Ok(())
 }
+
+/// Records failed delivery, unsubscribing chats we can't reach anymore, along
+/// with their topics, or just deleted topics, and following chat migrations.
+fn handle_send_error(db: &Db, sub: Subscriber, err: &RequestError) -> DbResult<()> {
+    db.record_error(sub, &err.to_string())?;
+    match err {
+        RequestError::Api(api_err) => match api_err {
+            ApiError::BotBlocked
+            | ApiError::BotKicked
+            | ApiError::BotKickedFromSupergroup
+            | ApiError::ChatNotFound
+            | ApiError::UserNotFound
+            | ApiError::UserDeactivated
+            | ApiError::GroupDeactivated
+            | ApiError::CantTalkWithBots => {
+                warn!(
+                    "unsubscribing {} from updates due to api error {}",
+                    sub.chat_id, api_err
+                );
+                for sub in db.chat_subscribers(sub.chat_id)? {
+                    db.unsubscribe(sub)?;
+                }
+                db.unfollow_chat(sub.chat_id)?;
+                db.record_event(&Event::AutoUnsubscribed {
+                    chat_id: sub.chat_id,
+                    reason: format!("{:?}", api_err),
+                })?;
+            }
+            ApiError::Unknown(text)
+                if sub.thread_id.is_some() && text.contains("message thread not found") =>
+            {
+                warn!("unsubscribing deleted topic {} from updates", sub);
+                db.unsubscribe(sub)?;
+                db.unfollow_all(sub)?;
+                db.record_event(&Event::AutoUnsubscribed {
+                    chat_id: sub.chat_id,
+                    reason: "TopicDeleted".to_string(),
+                })?;
+            }
+            _ => {}
+        },
+        RequestError::MigrateToChatId(new_chat_id) => {
+            warn!(
+                "chat_id {} migrated to new chat_id {}",
+                sub.chat_id, new_chat_id
+            );
+            db.replace_chat_id(sub.chat_id, *new_chat_id)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,855 @@
+use std::{cmp::Reverse, collections::BTreeMap, fmt::Write, time::Instant};
+
+use chrono::{Days, Duration, DurationRound, NaiveDate, TimeDelta, Utc};
+use teloxide::{
+    prelude::*,
+    requests::ResponseResult,
+    types::{
+        CallbackQuery, Chat, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, Message,
+        MessageId, Recipient, User,
+    },
+    utils::{command::BotCommands, markdown},
+};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, warn};
+
+use super::{
+    error_text, format_time, handle_send_error, launch_text, msg_lang, remove_pin, subscribe,
+    unsubscribe, Callback, MyBot, UnauthorizedCommand,
+};
+use crate::{
+    db::{AdminRole, AuditEntry, Db, DbResult, Event, Subscriber},
+    fetch::{RefreshRequest, API_URL},
+    i18n::Lang,
+    template::{Field, Template},
+};
+
+#[derive(BotCommands, Clone, Debug)]
+#[command(rename_rule = "snake_case", description = "Admin commands:")]
+pub(super) enum AdminCommand {
+    #[command(description = "help")]
+    Help,
+
+    #[command(description = "subscribers count")]
+    SubscribersCount,
+
+    #[command(description = "list subscribers, optionally starting from given page")]
+    Subscribers(String),
+
+    #[command(description = "subscriptions and notifications statistics")]
+    Stats,
+
+    #[command(description = "send MarkdownV2 formatted message to all subscribers")]
+    Broadcast(String),
+
+    #[command(description = "fetch launches right away")]
+    Refresh,
+
+    #[command(description = "show cached launches")]
+    Cache,
+
+    // was `/launch`, admin commands are matched first in admin chats and would
+    // hide the `/launch` everyone has
+    #[command(description = "dump cached launch json, formerly /launch")]
+    LaunchRaw(String),
+
+    #[command(description = "preview notification: <launch_id> [lead time, e.g. 1h]")]
+    Preview(String),
+
+    #[command(description = "show, set or reset notification template")]
+    Template(String),
+
+    #[command(description = "list admins")]
+    Admins,
+
+    #[command(description = "add admin chat, owners only")]
+    AdminAdd(String),
+
+    #[command(description = "remove admin chat, owners only")]
+    AdminRemove(String),
+
+    #[command(description = "show latest admin actions")]
+    Audit(String),
+
+    #[command(description = "subscribe channel by @username or id, bot must be its admin")]
+    ChannelAdd(String),
+
+    #[command(description = "unsubscribe channel by @username or id")]
+    ChannelRemove(String),
+}
+
+const SUBSCRIBERS_PAGE_SIZE: usize = 20;
+
+const AUDIT_DEFAULT_LIMIT: usize = 20;
+
+/// Longer launch dumps are sent as files, to fit into message length limit.
+const MAX_INLINE_JSON_LEN: usize = 3500;
+
+/// How often broadcast progress message gets updated.
+const BROADCAST_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[tracing::instrument(skip_all)]
+pub(super) async fn command_handler(
+    bot: MyBot,
+    msg: Message,
+    cmd: AdminCommand,
+    db: Db,
+    refresh: mpsc::Sender<RefreshRequest>,
+) -> ResponseResult<()> {
+    info!("handling: {:?}", cmd);
+    audit(&db, msg.chat.id.0, msg.from(), format!("{:?}", cmd));
+    match cmd {
+        AdminCommand::Help => {
+            let commands = [
+                AdminCommand::descriptions().to_string(),
+                UnauthorizedCommand::descriptions().to_string(),
+            ];
+            bot.send_message(msg.chat.id, markdown::escape(&commands.join("\n\n")))
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::SubscribersCount => {
+            let count = db
+                .subscribers_count()
+                .expect("failed getting subscribers count");
+            bot.send_message(msg.chat.id, format!("Total subscribers: {}", count))
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::Subscribers(page) => {
+            let page = page.trim().parse::<usize>().unwrap_or(1).max(1);
+            match subscribers_page(&db, page) {
+                Ok((text, keyboard)) => {
+                    bot.send_message(msg.chat.id, text)
+                        .reply_markup(keyboard)
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                }
+                Err(err) => {
+                    bot.send_message(msg.chat.id, error_text("Error listing subscribers", err))
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                }
+            }
+        }
+        AdminCommand::Stats => {
+            let text = stats_text(&db).unwrap_or_else(|err| error_text("Error getting stats", err));
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::Broadcast(text) => {
+            let text = text.trim();
+            if text.is_empty() {
+                bot.send_message(msg.chat.id, "Usage: `/broadcast <message>`")
+                    .reply_to_message_id(msg.id)
+                    .await?;
+                return Ok(());
+            }
+            let preview = match bot.send_message(msg.chat.id, text).await {
+                Ok(preview) => preview,
+                Err(err) => {
+                    bot.send_message(msg.chat.id, error_text("Error rendering message", err))
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                    return Ok(());
+                }
+            };
+            let count = match db.subscribers_count() {
+                Ok(count) => count,
+                Err(err) => {
+                    bot.send_message(msg.chat.id, error_text("Error counting subscribers", err))
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                    return Ok(());
+                }
+            };
+            if let Err(err) = db.set_pending_broadcast(msg.chat.id.0, preview.id.0) {
+                bot.send_message(msg.chat.id, error_text("Error saving broadcast", err))
+                    .reply_to_message_id(msg.id)
+                    .await?;
+                return Ok(());
+            }
+            bot.send_message(
+                msg.chat.id,
+                format!("Send the message above to {} subscribers?", count),
+            )
+            .reply_to_message_id(preview.id)
+            .reply_markup(InlineKeyboardMarkup::new([[
+                InlineKeyboardButton::callback(
+                    "Send",
+                    Callback::BroadcastSend(preview.id.0).encode(),
+                ),
+                InlineKeyboardButton::callback(
+                    "Cancel",
+                    Callback::BroadcastCancel(preview.id.0).encode(),
+                ),
+            ]]))
+            .await?;
+        }
+        AdminCommand::Refresh => {
+            let (tx, rx) = oneshot::channel();
+            let text = if refresh.send(tx).await.is_err() {
+                markdown::escape("Worker isn't running")
+            } else {
+                match rx.await {
+                    Ok(Ok(count)) => format!("Fetched {} launches", count),
+                    Ok(Err(err)) => error_text("Error fetching launches", err),
+                    Err(_) => markdown::escape("Worker stopped before fetching"),
+                }
+            };
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::Cache => {
+            let text = cache_text(&db).unwrap_or_else(|err| error_text("Error getting cache", err));
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::LaunchRaw(id) => {
+            let launch = match id.trim().parse::<u64>() {
+                Ok(id) => db
+                    .get_launches()
+                    .map(|launches| launches.into_iter().find(|l| l.id == id)),
+                Err(_) => {
+                    bot.send_message(msg.chat.id, "Usage: `/launch_raw <id>`")
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                    return Ok(());
+                }
+            };
+            match launch {
+                Ok(Some(launch)) => {
+                    let json = serde_json::to_string_pretty(&launch).unwrap_or_default();
+                    if json.len() > MAX_INLINE_JSON_LEN {
+                        bot.send_document(
+                            msg.chat.id,
+                            InputFile::memory(json).file_name(format!("launch-{}.json", launch.id)),
+                        )
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                    } else {
+                        bot.send_message(
+                            msg.chat.id,
+                            format!("```json\n{}\n```", markdown::escape_code(&json)),
+                        )
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                    }
+                }
+                Ok(None) => {
+                    bot.send_message(msg.chat.id, "No such launch in cache")
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                }
+                Err(err) => {
+                    bot.send_message(msg.chat.id, error_text("Error getting launches", err))
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                }
+            }
+        }
+        AdminCommand::Preview(args) => {
+            let text = preview_text(&db, &args, msg_lang(&db, &msg)).unwrap_or_else(|err| err);
+            // text Telegram can't parse is what previews are there to catch
+            if let Err(err) = bot
+                .send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await
+            {
+                bot.send_message(msg.chat.id, error_text("Notification was rejected", err))
+                    .reply_to_message_id(msg.id)
+                    .await?;
+            }
+        }
+        AdminCommand::Template(source) => {
+            let text = template_text(&db, &source).unwrap_or_else(|err| err);
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::Admins => {
+            let text =
+                admins_text(&db).unwrap_or_else(|err| error_text("Error listing admins", err));
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::AdminAdd(chat_id) => {
+            let text = manage_admin(&db, msg.chat.id.0, &chat_id, true).unwrap_or_else(|err| err);
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::AdminRemove(chat_id) => {
+            let text = manage_admin(&db, msg.chat.id.0, &chat_id, false).unwrap_or_else(|err| err);
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::Audit(limit) => {
+            let limit = limit.trim().parse().unwrap_or(AUDIT_DEFAULT_LIMIT);
+            let text =
+                audit_text(&db, limit).unwrap_or_else(|err| error_text("Error getting audit", err));
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::ChannelAdd(channel) => {
+            let text = add_channel(&bot, &db, &channel)
+                .await
+                .unwrap_or_else(|err| err);
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+        AdminCommand::ChannelRemove(channel) => {
+            let text = remove_channel(&bot, &db, &channel)
+                .await
+                .unwrap_or_else(|err| err);
+            bot.send_message(msg.chat.id, text)
+                .reply_to_message_id(msg.id)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub(super) async fn admin_callback_handler(
+    bot: MyBot,
+    q: CallbackQuery,
+    db: Db,
+) -> ResponseResult<()> {
+    bot.answer_callback_query(q.id).await?;
+    let (Some(msg), Some(callback)) = (q.message, q.data.as_deref().and_then(Callback::decode))
+    else {
+        return Ok(());
+    };
+    info!("handling callback: {:?}", callback);
+    if !matches!(callback, Callback::SubscribersPage(_)) {
+        audit(&db, msg.chat.id.0, Some(&q.from), format!("{:?}", callback));
+    }
+    match callback {
+        Callback::SubscribersPage(page) => {
+            if let Ok((text, keyboard)) = subscribers_page(&db, page) {
+                bot.edit_message_text(msg.chat.id, msg.id, text)
+                    .reply_markup(keyboard)
+                    .await?;
+            }
+        }
+        Callback::BroadcastSend(preview_id) | Callback::BroadcastCancel(preview_id) => {
+            // repeated presses arrive after the first one is handled
+            match db.take_pending_broadcast(msg.chat.id.0, preview_id) {
+                Ok(true) => {}
+                Ok(false) => return Ok(()),
+                Err(err) => {
+                    bot.edit_message_text(
+                        msg.chat.id,
+                        msg.id,
+                        error_text("Error getting broadcast", err),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            }
+            bot.edit_message_reply_markup(msg.chat.id, msg.id).await?;
+            if matches!(callback, Callback::BroadcastSend(_)) {
+                broadcast(&bot, &db, &msg, MessageId(preview_id)).await?;
+            } else {
+                bot.edit_message_text(msg.chat.id, msg.id, "Broadcast cancelled")
+                    .await?;
+            }
+        }
+        // not admin ones, handled by launch_callback_handler
+        Callback::LaunchShow(_)
+        | Callback::LaunchMute(_)
+        | Callback::LaunchUnmute(_)
+        | Callback::LaunchFollow(_)
+        | Callback::LaunchUnfollow(_) => {}
+    }
+    Ok(())
+}
+
+/// `@channel` or chat id argument of `command`, error is its usage message.
+fn parse_channel(channel: &str, command: &str) -> Result<Recipient, String> {
+    let channel = channel.trim();
+    if channel.starts_with('@') {
+        Ok(Recipient::ChannelUsername(channel.to_string()))
+    } else if let Ok(id) = channel.parse::<i64>() {
+        Ok(Recipient::Id(ChatId(id)))
+    } else {
+        Err(format!(
+            "Usage: `/{} @channel` or `/{} <chat_id>`",
+            command, command
+        ))
+    }
+}
+
+/// Looks up channel by `@username` or id, error is a ready to send message.
+async fn get_channel(bot: &MyBot, recipient: Recipient) -> Result<Chat, String> {
+    let chat = bot
+        .get_chat(recipient)
+        .await
+        .map_err(|err| error_text("Error getting channel", err))?;
+    if !chat.is_channel() {
+        return Err(markdown::escape("That's not a channel"));
+    }
+    Ok(chat)
+}
+
+/// Subscribes channel after checking that bot can post there, error is a ready
+/// to send message too.
+async fn add_channel(bot: &MyBot, db: &Db, channel: &str) -> Result<String, String> {
+    let chat = get_channel(bot, parse_channel(channel, "channel_add")?).await?;
+    let me = bot
+        .get_me()
+        .await
+        .map_err(|err| error_text("Error getting bot info", err))?;
+    let member = bot
+        .get_chat_member(chat.id, me.id)
+        .await
+        .map_err(|err| error_text("Error checking bot membership", err))?;
+    if !member.kind.can_post_messages() {
+        return Err(markdown::escape(
+            "Bot has to be a channel admin allowed to post messages",
+        ));
+    }
+    subscribe(db, Subscriber::chat(chat.id.0), &chat)
+        .map_err(|err| error_text("Error subscribing channel", err))?;
+    Ok(format!(
+        "Subscribed channel *{}* `{}`",
+        markdown::escape(chat.title().unwrap_or_default()),
+        chat.id
+    ))
+}
+
+/// Unsubscribes channel, by id without looking it up as the bot may have been
+/// removed from it already; error is a ready to send message too.
+async fn remove_channel(bot: &MyBot, db: &Db, channel: &str) -> Result<String, String> {
+    let chat_id = match parse_channel(channel, "channel_remove")? {
+        Recipient::Id(chat_id) => chat_id,
+        recipient => get_channel(bot, recipient).await?.id,
+    };
+    let sub = Subscriber::chat(chat_id.0);
+    remove_pin(bot, db, sub)
+        .await
+        .and_then(|_| unsubscribe(db, sub))
+        .map_err(|err| error_text("Error unsubscribing channel", err))?;
+    Ok(format!("Unsubscribed channel `{}`", chat_id))
+}
+
+fn audit(db: &Db, chat_id: i64, user: Option<&User>, action: String) {
+    let entry = AuditEntry {
+        at: Utc::now(),
+        chat_id,
+        user_id: user.map(|u| u.id.0),
+        username: user.and_then(|u| u.username.clone()),
+        action,
+    };
+    if let Err(err) = db.add_audit(&entry) {
+        warn!("failed recording audit entry {:?}: {}", entry, err);
+    }
+}
+
+/// Adds or removes admin `target`, on behalf of `chat_id`; error is a ready to
+/// send message too.
+fn manage_admin(db: &Db, chat_id: i64, target: &str, add: bool) -> Result<String, String> {
+    let role = db
+        .admin_role(chat_id)
+        .map_err(|err| error_text("Error checking role", err))?;
+    if role != Some(AdminRole::Owner) {
+        return Err(markdown::escape("Only owners can manage admins"));
+    }
+    let Ok(target) = target.trim().parse::<i64>() else {
+        return Err("Usage: `/admin_add <chat_id>` or `/admin_remove <chat_id>`".to_string());
+    };
+    let target_role = db
+        .admin_role(target)
+        .map_err(|err| error_text("Error checking role", err))?;
+    if target_role == Some(AdminRole::Owner) {
+        return Err(markdown::escape("Owners are managed in config file"));
+    }
+    if add {
+        db.set_admin(target, AdminRole::Admin)
+            .map_err(|err| error_text("Error adding admin", err))?;
+        Ok(format!("Added admin `{}`", target))
+    } else {
+        match db.remove_admin(target) {
+            Ok(true) => Ok(format!("Removed admin `{}`", target)),
+            Ok(false) => Ok(format!("`{}` isn't an admin", target)),
+            Err(err) => Err(error_text("Error removing admin", err)),
+        }
+    }
+}
+
+fn admins_text(db: &Db) -> DbResult<String> {
+    let mut text = "*Admins*".to_string();
+    for (chat_id, role) in db.admins()? {
+        let _ = write!(text, "\n`{}` {}", chat_id, role.as_str());
+    }
+    Ok(text)
+}
+
+fn audit_text(db: &Db, limit: usize) -> DbResult<String> {
+    let entries = db.audit_log(limit)?;
+    let mut text = format!("*Last {} admin actions*", entries.len());
+    for entry in entries {
+        let _ = write!(
+            text,
+            "\n\n{} `{}`",
+            markdown::escape(&format_time(Some(entry.at))),
+            entry.chat_id,
+        );
+        match (&entry.username, entry.user_id) {
+            (Some(username), _) => {
+                let _ = write!(text, " @{}", markdown::escape(username));
+            }
+            (None, Some(user_id)) => {
+                let _ = write!(text, " user `{}`", user_id);
+            }
+            (None, None) => {}
+        }
+        let _ = write!(text, "\n{}", markdown::escape(&entry.action));
+    }
+    Ok(text)
+}
+
+/// Copies preview message to every subscriber, reporting progress by editing
+/// `status` message.
+#[tracing::instrument(skip_all)]
+async fn broadcast(
+    bot: &MyBot,
+    db: &Db,
+    status: &Message,
+    preview_id: MessageId,
+) -> ResponseResult<()> {
+    let subscribers = match db.subscribers() {
+        Ok(subscribers) => subscribers,
+        Err(err) => {
+            bot.edit_message_text(
+                status.chat.id,
+                status.id,
+                error_text("Error getting subscribers", err),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let total = subscribers.len();
+    info!("broadcasting message {} to {} chats", preview_id, total);
+
+    let progress = |sent: usize, failed: usize| {
+        markdown::escape(&format!(
+            "Broadcasting: {}/{} sent, {} failed",
+            sent, total, failed
+        ))
+    };
+    bot.edit_message_text(status.chat.id, status.id, progress(0, 0))
+        .await?;
+
+    let (mut sent, mut failed) = (0, 0);
+    let mut last_report = Instant::now();
+    for sub in subscribers {
+        let request = bot.copy_message(ChatId(sub.chat_id), status.chat.id, preview_id);
+        let request = match sub.thread_id {
+            Some(thread_id) => request.message_thread_id(thread_id),
+            None => request,
+        };
+        match request.await {
+            Ok(_) => {
+                sent += 1;
+                if let Err(err) = db.record_delivery(sub) {
+                    warn!("failed recording delivery to {}: {}", sub, err);
+                }
+            }
+            Err(err) => {
+                failed += 1;
+                warn!("failed broadcasting to {}: {}", sub, err);
+                if let Err(err) = handle_send_error(db, sub, &err) {
+                    warn!("failed handling send error for {}: {}", sub, err);
+                }
+            }
+        }
+        if last_report.elapsed() >= BROADCAST_PROGRESS_INTERVAL {
+            last_report = Instant::now();
+            let _ = bot
+                .edit_message_text(status.chat.id, status.id, progress(sent, failed))
+                .await;
+        }
+    }
+
+    bot.edit_message_text(
+        status.chat.id,
+        status.id,
+        markdown::escape(&format!(
+            "Broadcast done: {}/{} sent, {} failed",
+            sent, total, failed
+        )),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Renders notification text for `<launch_id> [lead_time]` in `lang`, error is
+/// a ready to send message too.
+fn preview_text(db: &Db, args: &str, lang: Lang) -> Result<String, String> {
+    let mut args = args.split_whitespace();
+    let Some(launch_id) = args.next().and_then(|id| id.parse::<u64>().ok()) else {
+        return Err("Usage: `/preview <launch_id> [lead_time]`".to_string());
+    };
+    let lead_time = args
+        .next()
+        .map(|lead_time| {
+            humantime::parse_duration(lead_time)
+                .map_err(|err| error_text("Bad lead time", err))
+                .and_then(|lead_time| {
+                    Duration::from_std(lead_time).map_err(|err| error_text("Bad lead time", err))
+                })
+        })
+        .transpose()?;
+
+    let launch = db
+        .get_launches()
+        .map_err(|err| error_text("Error getting launches", err))?
+        .into_iter()
+        .find(|l| l.id == launch_id)
+        .ok_or_else(|| "No such launch in cache".to_string())?;
+    let Some(t0) = launch.t0 else {
+        return Err(markdown::escape(
+            "Launch has no T-0, no notifications would be sent",
+        ));
+    };
+    let now = match lead_time {
+        Some(lead_time) => t0
+            .checked_sub_signed(lead_time)
+            .ok_or_else(|| markdown::escape("Bad lead time: too long"))?,
+        None => Utc::now()
+            .duration_round(TimeDelta::try_minutes(1).unwrap())
+            .map_err(|err| error_text("Error rendering notification", err))?,
+    };
+    // notifications fall back to built in format, previews tell why
+    let template = db
+        .notification_template()
+        .map_err(|err| error_text("Error getting template", err))?
+        .map(|source| Template::parse(&source))
+        .transpose()
+        .map_err(|err| {
+            markdown::escape(&format!(
+                "Bad template, notifications use built in format: {}",
+                err
+            ))
+        })?;
+    launch_text(&launch, t0, now, lang, template.as_ref())
+        .map_err(|err| error_text("Error rendering notification", err))
+}
+
+/// `/template` shows the current one, `/template reset` brings back built in
+/// format, anything else is a new template.
+fn template_text(db: &Db, args: &str) -> Result<String, String> {
+    let placeholders = Field::ALL
+        .iter()
+        .map(|field| format!("`{{{}}}`", field.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    match args.trim() {
+        "" => {
+            let current = db
+                .notification_template()
+                .map_err(|err| error_text("Error getting template", err))?;
+            let current = match current {
+                Some(source) => format!(
+                    "*Notification template*\n```\n{}\n```",
+                    markdown::escape_code(&source)
+                ),
+                None => markdown::escape("Built in notification format is used"),
+            };
+            Ok(format!(
+                "{}\n\n{}\n{}",
+                current,
+                markdown::escape(concat!(
+                    "Usage: /template <MarkdownV2 text with placeholders>, /template reset. ",
+                    "Placeholders:"
+                )),
+                placeholders
+            ))
+        }
+        "reset" => {
+            db.set_notification_template(None)
+                .map_err(|err| error_text("Error saving template", err))?;
+            Ok(markdown::escape("Built in notification format is used now"))
+        }
+        source => {
+            let template = Template::parse(source)
+                .map_err(|err| markdown::escape(&format!("Bad template: {}", err)))?;
+            db.set_notification_template(Some(template.source()))
+                .map_err(|err| error_text("Error saving template", err))?;
+            Ok(markdown::escape(
+                "Notification template saved, see /preview",
+            ))
+        }
+    }
+}
+
+fn cache_text(db: &Db) -> DbResult<String> {
+    let launches = db.get_launches()?;
+    let mut text = format!(
+        "*{} cached launches*\nsource: {}\nfetched: {}",
+        launches.len(),
+        markdown::escape(API_URL),
+        markdown::escape(&format_time(db.last_fetch()?)),
+    );
+    for launch in &launches {
+        let _ = write!(
+            text,
+            "\n\n`{}` {}\nmodified {}",
+            launch.id,
+            markdown::escape(&launch.name),
+            markdown::escape(&format_time(Some(launch.modified))),
+        );
+    }
+    Ok(text)
+}
+
+fn stats_text(db: &Db) -> DbResult<String> {
+    let now = Utc::now();
+    let today = now.date_naive();
+    let week_ago = now - Duration::try_days(7).unwrap();
+    let month_ago = now - Duration::try_days(30).unwrap();
+
+    let mut by_type: BTreeMap<String, usize> = BTreeMap::new();
+    let subscribers = db.subscribers()?;
+    for &sub in &subscribers {
+        let info = db.get_subscriber_info(sub)?;
+        *by_type.entry(info.chat_type.to_string()).or_default() += 1;
+    }
+
+    let (mut subs_week, mut subs_month, mut unsubs_week, mut unsubs_month) = (0, 0, 0, 0);
+    let mut per_day: BTreeMap<NaiveDate, (usize, usize)> = (0..7)
+        .filter_map(|days| today.checked_sub_days(Days::new(days)))
+        .map(|day| (day, (0, 0)))
+        .collect();
+    let mut auto_unsubs: BTreeMap<String, usize> = BTreeMap::new();
+    for (at, event) in db.events_since(month_ago)? {
+        let this_week = at >= week_ago;
+        match event {
+            Event::Subscribed { .. } => {
+                subs_month += 1;
+                subs_week += usize::from(this_week);
+            }
+            Event::Unsubscribed { .. } => {
+                unsubs_month += 1;
+                unsubs_week += usize::from(this_week);
+            }
+            Event::AutoUnsubscribed { reason, .. } => {
+                unsubs_month += 1;
+                unsubs_week += usize::from(this_week);
+                *auto_unsubs.entry(reason).or_default() += 1;
+            }
+            Event::NotificationSent { .. } => {
+                if let Some((sent, _)) = per_day.get_mut(&at.date_naive()) {
+                    *sent += 1;
+                }
+            }
+            Event::NotificationFailed { .. } => {
+                if let Some((_, failed)) = per_day.get_mut(&at.date_naive()) {
+                    *failed += 1;
+                }
+            }
+        }
+    }
+
+    let mut text = format!("*Subscribers*: {}", subscribers.len());
+    for (chat_type, count) in &by_type {
+        let _ = write!(text, "\n{}: {}", chat_type, count);
+    }
+    let _ = write!(
+        text,
+        "\n\n*Subscribed / unsubscribed*\n7 days: {} / {}\n30 days: {} / {}",
+        subs_week, unsubs_week, subs_month, unsubs_month
+    );
+    let _ = write!(text, "\n\n*Notifications sent / failed*");
+    for (day, (sent, failed)) in per_day.iter().rev() {
+        let _ = write!(
+            text,
+            "\n{}: {} / {}",
+            markdown::escape(&day.to_string()),
+            sent,
+            failed
+        );
+    }
+    let _ = write!(text, "\n\n*Auto\\-unsubscribes, 30 days*");
+    if auto_unsubs.is_empty() {
+        let _ = write!(text, "\nnone");
+    }
+    for (reason, count) in &auto_unsubs {
+        let _ = write!(text, "\n{}: {}", markdown::escape(reason), count);
+    }
+    let _ = write!(
+        text,
+        "\n\n*Last fetch*: {}",
+        markdown::escape(&format_time(db.last_fetch()?))
+    );
+    Ok(text)
+}
+
+fn subscribers_page(db: &Db, page: usize) -> DbResult<(String, InlineKeyboardMarkup)> {
+    let mut subscribers = db
+        .subscribers()?
+        .into_iter()
+        .map(|sub| Ok((sub, db.get_subscriber_info(sub)?)))
+        .collect::<DbResult<Vec<_>>>()?;
+    subscribers.sort_by_key(|(_, info)| Reverse(info.subscribed_at));
+
+    let pages = subscribers.len().div_ceil(SUBSCRIBERS_PAGE_SIZE).max(1);
+    let page = page.min(pages);
+    let offset = (page - 1) * SUBSCRIBERS_PAGE_SIZE;
+
+    let mut text = format!(
+        "Subscribers, page {}/{}, total {}:",
+        page,
+        pages,
+        subscribers.len()
+    );
+    for (sub, info) in subscribers.iter().skip(offset).take(SUBSCRIBERS_PAGE_SIZE) {
+        let _ = write!(text, "\n\n`{}` {}", sub, info.chat_type);
+        if let Some(title) = &info.title {
+            let _ = write!(text, " *{}*", markdown::escape(title));
+        }
+        if let Some(username) = &info.username {
+            let _ = write!(text, " @{}", markdown::escape(username));
+        }
+        let _ = write!(
+            text,
+            "\nsubscribed {}, delivered {}",
+            markdown::escape(&format_time(info.subscribed_at)),
+            markdown::escape(&format_time(info.last_delivery)),
+        );
+        if let Some(error) = &info.last_error {
+            let _ = write!(
+                text,
+                "\nerror {}: {}",
+                markdown::escape(&format_time(info.last_error_at)),
+                markdown::escape(error),
+            );
+        }
+    }
+
+    let mut buttons = vec![];
+    if page > 1 {
+        buttons.push(InlineKeyboardButton::callback(
+            "« prev",
+            Callback::SubscribersPage(page - 1).encode(),
+        ));
+    }
+    if page < pages {
+        buttons.push(InlineKeyboardButton::callback(
+            "next »",
+            Callback::SubscribersPage(page + 1).encode(),
+        ));
+    }
+
+    Ok((text, InlineKeyboardMarkup::new([buttons])))
+}
@@ -0,0 +1,131 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use teloxide::{prelude::*, types::Message, utils::markdown};
+
+use super::{error_text, fill, msg_lang, msg_subscriber, reply, update_settings, MyBot};
+use crate::{
+    db::{ChatSettings, Db, Digest, DigestPeriod},
+    i18n::Lang,
+    types::Launch,
+};
+
+fn digest_usage(lang: Lang) -> String {
+    let tr = lang.catalog();
+    format!(
+        "{}: `/digest daily <HH:MM>`, `/digest weekly <day> <HH:MM>`, `/digest off`\n\n{}",
+        markdown::escape(tr.usage),
+        markdown::escape(tr.digest_help)
+    )
+}
+
+/// `daily 08:00` or `weekly mon 08:00`
+fn parse_digest(args: &str) -> Option<Digest> {
+    let words = args.split_whitespace().collect::<Vec<_>>();
+    let (period, time) = match words.as_slice() {
+        ["daily", time] => (DigestPeriod::Daily, time),
+        ["weekly", day, time] => (DigestPeriod::Weekly(day.parse::<Weekday>().ok()?), time),
+        _ => return None,
+    };
+    Some(Digest {
+        period,
+        time: NaiveTime::parse_from_str(time, "%H:%M").ok()?,
+    })
+}
+
+pub(super) fn digest_schedule_text(digest: &Digest, lang: Lang) -> String {
+    let tr = lang.catalog();
+    let time = markdown::escape(&digest.time.format("%H:%M").to_string());
+    match digest.period {
+        DigestPeriod::Daily => fill(tr.digest_daily, &[&time]),
+        DigestPeriod::Weekly(weekday) => fill(
+            tr.digest_weekly,
+            &[&markdown::escape(tr.weekday(weekday)), &time],
+        ),
+    }
+}
+
+/// Launches of the `digest` span after `now` the subscription would be
+/// notified about, soonest first, with times in the chat timezone and text in
+/// its language.
+pub(super) fn digest_text(
+    launches: &[Launch],
+    settings: &ChatSettings,
+    digest: &Digest,
+    now: DateTime<Utc>,
+) -> String {
+    let until = now + digest.span();
+    let mut upcoming = launches
+        .iter()
+        .filter(|l| l.t0.is_some_and(|t0| t0 > now && t0 <= until))
+        .filter(|l| !settings.filters_out(l) && !settings.muted_launches.contains(&l.id))
+        .collect::<Vec<_>>();
+    upcoming.sort_by_key(|l| l.t0);
+
+    let tr = settings.language.unwrap_or_default().catalog();
+    let (title, empty) = match digest.period {
+        DigestPeriod::Daily => (tr.digest_day, tr.digest_day_empty),
+        DigestPeriod::Weekly(_) => (tr.digest_week, tr.digest_week_empty),
+    };
+    if upcoming.is_empty() {
+        return markdown::escape(empty);
+    }
+    let mut text = format!("*{}*", markdown::escape(title));
+    for launch in upcoming {
+        let Some(t0) = launch.t0 else {
+            continue;
+        };
+        let _ = write!(
+            text,
+            "\n`{}` [{} \\- {}](https://rocketlaunch.live/launch/{}) {}",
+            markdown::escape_code(&format!(
+                "{} {}",
+                tr.weekday(t0.with_timezone(&settings.timezone).weekday()),
+                t0.with_timezone(&settings.timezone).format("%H:%M")
+            )),
+            markdown::escape(&launch.provider.name),
+            markdown::escape(&launch.vehicle.name),
+            markdown::escape(&launch.slug),
+            markdown::escape(&launch.name),
+        );
+    }
+    text
+}
+
+/// `/digest daily <time>`, `/digest weekly <day> <time>` and `/digest off`.
+pub(super) async fn digest_command(
+    bot: &MyBot,
+    db: &Db,
+    msg: &Message,
+    args: &str,
+) -> ResponseResult<()> {
+    let lang = msg_lang(db, msg);
+    let tr = lang.catalog();
+    let sub = msg_subscriber(msg);
+    let args = args.trim();
+    let digest = match args {
+        "off" => None,
+        _ => match parse_digest(args) {
+            Some(digest) => Some(digest),
+            None => {
+                reply(bot, msg, digest_usage(lang)).await?;
+                return Ok(());
+            }
+        },
+    };
+    let text = match update_settings(db, sub, |settings| settings.digest = digest) {
+        Ok(()) => match &digest {
+            Some(digest) if db.is_subscribed(sub).unwrap_or(false) => {
+                fill(tr.digest_set, &[&digest_schedule_text(digest, lang)])
+            }
+            Some(digest) => fill(
+                tr.digest_set_unsubscribed,
+                &[&digest_schedule_text(digest, lang)],
+            ),
+            None => markdown::escape(tr.digest_off),
+        },
+        Err(err) => error_text(tr.error_saving_settings, err),
+    };
+    reply(bot, msg, text).await?;
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use chrono::{DurationRound, TimeDelta, Utc};
+use teloxide::{
+    prelude::*,
+    types::{
+        InlineQuery, InlineQueryResult, InlineQueryResultArticle, InputMessageContent,
+        InputMessageContentText, ParseMode,
+    },
+};
+use tracing::{info, warn};
+
+use super::{launch_text, notification_template, search::LaunchFilter, MyBot};
+use crate::{db::Db, i18n::Lang};
+
+/// Telegram doesn't accept more results per answer.
+const MAX_RESULTS: usize = 50;
+/// Results contain a countdown, so they shouldn't be cached for long.
+const CACHE_TIME_SECS: u32 = 60;
+
+/// Answers `@bot <query>` with upcoming launches matching the query, soonest
+/// first, formatted the same way as notifications. Query is the same as for
+/// `/find`, a malformed one finds nothing.
+#[tracing::instrument(skip_all)]
+pub(super) async fn inline_query_handler(bot: MyBot, q: InlineQuery, db: Db) -> ResponseResult<()> {
+    info!("handling inline query: {:?}", q.query);
+    let now = Utc::now()
+        .duration_round(TimeDelta::try_minutes(1).unwrap())
+        .unwrap_or_else(|_| Utc::now());
+    let lang = q
+        .from
+        .language_code
+        .as_deref()
+        .and_then(Lang::from_code)
+        .unwrap_or_default();
+    let filter = LaunchFilter::parse(&q.query);
+    let mut launches = db
+        .get_launches()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|l| l.t0.is_some() && filter.as_ref().is_ok_and(|filter| filter.matches(l, now)))
+        .collect::<Vec<_>>();
+    launches.sort_by_key(|l| l.t0);
+
+    let template = notification_template(&db);
+    let mut results = vec![];
+    for launch in launches.iter().take(MAX_RESULTS) {
+        let Some(t0) = launch.t0 else {
+            continue;
+        };
+        let text = match launch_text(launch, t0, now, lang, template.as_ref()) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("failed rendering launch {}: {}", launch.id, err);
+                continue;
+            }
+        };
+        let content = InputMessageContent::Text(
+            InputMessageContentText::new(text).parse_mode(ParseMode::MarkdownV2),
+        );
+        let article = InlineQueryResultArticle::new(
+            launch.id.to_string(),
+            format!("{} - {}", launch.provider.name, launch.vehicle.name),
+            content,
+        )
+        .description(format!(
+            "{}\n{}\n{}",
+            launch.name,
+            t0.format("%Y-%m-%d %H:%M UTC"),
+            launch.pad
+        ));
+        results.push(InlineQueryResult::Article(article));
+    }
+
+    bot.answer_inline_query(q.id, results)
+        .cache_time(CACHE_TIME_SECS)
+        .await?;
+    Ok(())
+}
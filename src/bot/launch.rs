@@ -0,0 +1,462 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use teloxide::{
+    prelude::*,
+    types::{
+        CallbackQuery, InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+        Message,
+    },
+    utils::markdown,
+};
+use tracing::info;
+
+use super::{
+    error_text, fill, msg_lang, msg_subscriber, reply, settings::user_can_manage, update_settings,
+    user_lang, Callback, MyBot,
+};
+use crate::{
+    db::{Db, DbResult, Follow, Subscriber},
+    i18n::Lang,
+    types::Launch,
+};
+
+/// Launch `query` refers to by id or slug, the next upcoming one if it's empty.
+fn find_launch<'a>(launches: &'a [Launch], query: &str) -> Option<&'a Launch> {
+    let query = query.trim();
+    if query.is_empty() {
+        let now = Utc::now();
+        return launches
+            .iter()
+            .filter(|l| l.t0.is_some_and(|t0| t0 >= now))
+            .min_by_key(|l| l.t0);
+    }
+    match query.parse::<u64>() {
+        Ok(id) => launches.iter().find(|l| l.id == id),
+        Err(_) => launches.iter().find(|l| l.slug.eq_ignore_ascii_case(query)),
+    }
+}
+
+/// Cached launches in the order they're navigated with buttons.
+fn sorted_launches(db: &Db) -> DbResult<Vec<Launch>> {
+    let mut launches = db.get_launches()?;
+    launches.sort_by_key(|l| l.sort_date);
+    Ok(launches)
+}
+
+fn relative_time(t: DateTime<Utc>, now: DateTime<Utc>, lang: Lang) -> String {
+    let tr = lang.catalog();
+    let secs = |d: chrono::Duration| std::time::Duration::from_secs(d.num_minutes() as u64 * 60);
+    if t >= now {
+        format!("{} {}", tr.countdown_in, tr.duration(secs(t - now)))
+    } else {
+        format!("{} {}", tr.duration(secs(now - t)), tr.ago)
+    }
+}
+
+/// Everything known about `launch`.
+fn launch_details(launch: &Launch, lang: Lang) -> String {
+    let tr = lang.catalog();
+    let now = Utc::now();
+    let mut text = format!(
+        "*[{} \\- {}](https://rocketlaunch.live/launch/{})*\n{}\nid `{}`",
+        markdown::escape(&launch.provider.name),
+        markdown::escape(&launch.vehicle.name),
+        markdown::escape(&launch.slug),
+        markdown::escape(&launch.name),
+        launch.id,
+    );
+
+    match launch.t0 {
+        Some(t0) => {
+            let _ = write!(
+                text,
+                "\n\n*T\\-0*: {} \\({}\\)",
+                markdown::escape(&t0.format("%Y-%m-%d %H:%M UTC").to_string()),
+                markdown::escape(&relative_time(t0, now, lang)),
+            );
+        }
+        None => {
+            let _ = write!(
+                text,
+                "\n\n*{}*: {}",
+                markdown::escape(tr.date),
+                markdown::escape(&launch.date_str)
+            );
+        }
+    }
+    if let (Some(open), Some(close)) = (launch.win_open, launch.win_close) {
+        let _ = write!(
+            text,
+            "\n*{}*: {} – {}",
+            markdown::escape(tr.window),
+            markdown::escape(&open.format("%Y-%m-%d %H:%M").to_string()),
+            markdown::escape(&close.format("%H:%M UTC").to_string()),
+        );
+    }
+    let _ = write!(
+        text,
+        "\n*{}*: {}",
+        markdown::escape(tr.pad),
+        markdown::escape(&launch.pad.to_string())
+    );
+    if launch.suborbital {
+        let _ = write!(text, "\n{}", markdown::escape(tr.suborbital));
+    }
+
+    for mission in &launch.missions {
+        let _ = write!(
+            text,
+            "\n\n*{} {}*",
+            markdown::escape(tr.mission),
+            markdown::escape(&mission.name)
+        );
+        if let Some(desc) = &mission.description {
+            let _ = write!(text, "\n{}", markdown::escape(desc));
+        }
+    }
+
+    if !launch.launch_description.is_empty() {
+        let _ = write!(text, "\n\n{}", markdown::escape(&launch.launch_description));
+    }
+    if !launch.quicktext.is_empty() {
+        let _ = write!(text, "\n\n_{}_", markdown::escape(&launch.quicktext));
+    }
+    if !launch.tags.is_empty() {
+        let tags = launch
+            .tags
+            .iter()
+            .map(|tag| tag.text.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = write!(
+            text,
+            "\n\n*{}*: {}",
+            markdown::escape(tr.tags),
+            markdown::escape(&tags)
+        );
+    }
+    let _ = write!(
+        text,
+        "\n\n{} {}",
+        markdown::escape(tr.updated),
+        markdown::escape(&relative_time(launch.modified, now, lang))
+    );
+    text
+}
+
+/// Mute toggle, showing the action opposite to current state.
+fn mute_button(launch_id: u64, muted: bool, lang: Lang) -> InlineKeyboardButton {
+    let tr = lang.catalog();
+    if muted {
+        InlineKeyboardButton::callback(tr.button_unmute, Callback::LaunchUnmute(launch_id).encode())
+    } else {
+        InlineKeyboardButton::callback(tr.button_mute, Callback::LaunchMute(launch_id).encode())
+    }
+}
+
+/// Follow toggle, showing the action opposite to current state.
+pub(super) fn follow_button(launch_id: u64, following: bool, lang: Lang) -> InlineKeyboardButton {
+    let tr = lang.catalog();
+    if following {
+        InlineKeyboardButton::callback(
+            tr.button_unfollow,
+            Callback::LaunchUnfollow(launch_id).encode(),
+        )
+    } else {
+        InlineKeyboardButton::callback(tr.button_follow, Callback::LaunchFollow(launch_id).encode())
+    }
+}
+
+/// Previous/next launch buttons, following, and muting for subscribed chats,
+/// `muted` is `None` for others.
+fn launch_keyboard(
+    launches: &[Launch],
+    launch: &Launch,
+    muted: Option<bool>,
+    following: bool,
+    lang: Lang,
+) -> InlineKeyboardMarkup {
+    let tr = lang.catalog();
+    let pos = launches.iter().position(|l| l.id == launch.id);
+    let mut nav = vec![];
+    if let Some(prev) = pos
+        .and_then(|pos| pos.checked_sub(1))
+        .map(|pos| &launches[pos])
+    {
+        nav.push(InlineKeyboardButton::callback(
+            tr.button_previous,
+            Callback::LaunchShow(prev.id).encode(),
+        ));
+    }
+    if let Some(next) = pos.and_then(|pos| launches.get(pos + 1)) {
+        nav.push(InlineKeyboardButton::callback(
+            tr.button_next,
+            Callback::LaunchShow(next.id).encode(),
+        ));
+    }
+    let mut rows = vec![];
+    if !nav.is_empty() {
+        rows.push(nav);
+    }
+    let mut toggles = vec![follow_button(launch.id, following, lang)];
+    toggles.extend(muted.map(|muted| mute_button(launch.id, muted, lang)));
+    rows.push(toggles);
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Whether `launch_id` is muted for subscription `msg` belongs to, `None` if
+/// there's no subscription.
+fn muted(db: &Db, msg: &Message, launch_id: u64) -> DbResult<Option<bool>> {
+    let sub = msg_subscriber(msg);
+    if !db.is_subscribed(sub)? {
+        return Ok(None);
+    }
+    let settings = db.get_settings(sub)?;
+    Ok(Some(settings.muted_launches.contains(&launch_id)))
+}
+
+fn launch_view(
+    db: &Db,
+    msg: &Message,
+    query: &str,
+    lang: Lang,
+) -> DbResult<Option<(String, InlineKeyboardMarkup)>> {
+    let launches = sorted_launches(db)?;
+    let Some(launch) = find_launch(&launches, query) else {
+        return Ok(None);
+    };
+    let following = db
+        .get_follows(msg_subscriber(msg))?
+        .contains_key(&launch.id);
+    let muted = muted(db, msg, launch.id)?;
+    let keyboard = launch_keyboard(&launches, launch, muted, following, lang);
+    Ok(Some((launch_details(launch, lang), keyboard)))
+}
+
+/// `/launch [id|slug]`, details of the next launch without arguments.
+pub(super) async fn launch_command(
+    bot: &MyBot,
+    db: &Db,
+    msg: &Message,
+    query: &str,
+) -> ResponseResult<()> {
+    let lang = msg_lang(db, msg);
+    let tr = lang.catalog();
+    match launch_view(db, msg, query, lang) {
+        Ok(Some((text, keyboard))) => {
+            reply(bot, msg, text)
+                .reply_markup(keyboard)
+                .disable_web_page_preview(true)
+                .await?;
+        }
+        Ok(None) => {
+            let text = format!(
+                "{}\n\n{}: `/launch [id|slug]`",
+                markdown::escape(tr.no_such_launch),
+                markdown::escape(tr.usage)
+            );
+            reply(bot, msg, text).await?;
+        }
+        Err(err) => {
+            reply(bot, msg, error_text(tr.error_getting_launch, err)).await?;
+        }
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub(super) async fn launch_callback_handler(
+    bot: MyBot,
+    q: CallbackQuery,
+    db: Db,
+) -> ResponseResult<()> {
+    let (Some(msg), Some(callback)) = (q.message, q.data.as_deref().and_then(Callback::decode))
+    else {
+        bot.answer_callback_query(q.id).await?;
+        return Ok(());
+    };
+    info!("handling callback: {:?}", callback);
+    let lang = user_lang(&db, msg_subscriber(&msg), Some(&q.from));
+    let tr = lang.catalog();
+    let (res, done, buttons) = match callback {
+        Callback::LaunchShow(launch_id) => {
+            bot.answer_callback_query(q.id).await?;
+            let view = launch_view(&db, &msg, &launch_id.to_string(), lang);
+            if let Ok(Some((text, keyboard))) = view {
+                bot.edit_message_text(msg.chat.id, msg.id, text)
+                    .reply_markup(keyboard)
+                    .disable_web_page_preview(true)
+                    .await?;
+            }
+            return Ok(());
+        }
+        Callback::LaunchMute(_)
+        | Callback::LaunchUnmute(_)
+        | Callback::LaunchFollow(_)
+        | Callback::LaunchUnfollow(_)
+            if !user_can_manage(&bot, &db, &msg.chat, &q.from).await? =>
+        {
+            bot.answer_callback_query(q.id).text(tr.admins_only).await?;
+            return Ok(());
+        }
+        Callback::LaunchMute(launch_id) => (
+            set_muted(&db, msg_subscriber(&msg), launch_id, true),
+            tr.launch_muted,
+            vec![(callback.clone(), mute_button(launch_id, true, lang))],
+        ),
+        Callback::LaunchUnmute(launch_id) => (
+            set_muted(&db, msg_subscriber(&msg), launch_id, false),
+            tr.launch_unmuted,
+            vec![(callback.clone(), mute_button(launch_id, false, lang))],
+        ),
+        Callback::LaunchFollow(launch_id) => (
+            follow(&db, msg_subscriber(&msg), launch_id).map(|_| ()),
+            tr.launch_followed,
+            // following unmutes
+            vec![
+                (callback.clone(), follow_button(launch_id, true, lang)),
+                (
+                    Callback::LaunchUnmute(launch_id),
+                    mute_button(launch_id, false, lang),
+                ),
+            ],
+        ),
+        Callback::LaunchUnfollow(launch_id) if follows_matching(&db, &msg, launch_id) => {
+            bot.answer_callback_query(q.id)
+                .text(tr.matched_launch_unfollow)
+                .await?;
+            return Ok(());
+        }
+        Callback::LaunchUnfollow(launch_id) => (
+            db.unfollow(msg_subscriber(&msg), launch_id).map(|_| ()),
+            tr.launch_unfollowed,
+            vec![(callback.clone(), follow_button(launch_id, false, lang))],
+        ),
+        _ => {
+            bot.answer_callback_query(q.id).await?;
+            return Ok(());
+        }
+    };
+
+    let text = match res {
+        Ok(()) => done.to_string(),
+        Err(err) => format!("{}: {}", tr.error_saving_settings, err),
+    };
+    bot.answer_callback_query(q.id).text(text).await?;
+    if let Some(keyboard) = swap_buttons(&msg, buttons) {
+        bot.edit_message_reply_markup(msg.chat.id, msg.id)
+            .reply_markup(keyboard)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Keyboard of `msg` with buttons sending given callbacks replaced, `None` if
+/// it has none of them.
+fn swap_buttons(
+    msg: &Message,
+    buttons: Vec<(Callback, InlineKeyboardButton)>,
+) -> Option<InlineKeyboardMarkup> {
+    let mut keyboard = msg.reply_markup()?.clone();
+    let mut swapped = false;
+    for (callback, button) in buttons {
+        let data = callback.encode();
+        let old =
+            keyboard.inline_keyboard.iter_mut().flatten().find(
+                |b| matches!(&b.kind, InlineKeyboardButtonKind::CallbackData(d) if *d == data),
+            );
+        if let Some(old) = old {
+            *old = button;
+            swapped = true;
+        }
+    }
+    swapped.then_some(keyboard)
+}
+
+fn set_muted(db: &Db, sub: Subscriber, launch_id: u64, mute: bool) -> DbResult<()> {
+    update_settings(db, sub, |settings| {
+        settings.muted_launches.retain(|&id| id != launch_id);
+        if mute {
+            settings.muted_launches.push(launch_id);
+        }
+    })
+}
+
+/// Whether chat of `msg` follows the launch for its vehicle or tags, so it
+/// would get followed again after unfollowing.
+fn follows_matching(db: &Db, msg: &Message, launch_id: u64) -> bool {
+    let Ok(settings) = db.get_settings(msg_subscriber(msg)) else {
+        return false;
+    };
+    db.get_launches()
+        .unwrap_or_default()
+        .iter()
+        .find(|l| l.id == launch_id)
+        .is_some_and(|launch| settings.follows_launch(launch))
+}
+
+/// Follows launch, keeping notification progress of already followed one,
+/// so it stays followed when its vehicle or tag doesn't. Muted launch gets
+/// unmuted, mutes win over follows. Returns `false` if it was followed with
+/// `/follow` already.
+fn follow(db: &Db, sub: Subscriber, launch_id: u64) -> DbResult<bool> {
+    if db.get_settings(sub)?.muted_launches.contains(&launch_id) {
+        set_muted(db, sub, launch_id, false)?;
+    }
+    let follow = db.get_follows(sub)?.get(&launch_id).copied();
+    if follow.is_some_and(|follow| !follow.matched) {
+        return Ok(false);
+    }
+    let follow = Follow {
+        matched: false,
+        ..follow.unwrap_or_default()
+    };
+    db.set_follow(sub, launch_id, follow)?;
+    Ok(true)
+}
+
+/// `/follow <id|slug>` and `/unfollow <id|slug>`.
+pub(super) async fn follow_command(
+    bot: &MyBot,
+    db: &Db,
+    msg: &Message,
+    query: &str,
+    start: bool,
+) -> ResponseResult<()> {
+    let tr = msg_lang(db, msg).catalog();
+    let sub = msg_subscriber(msg);
+    let name = |launch: &Launch| format!("*{}*", markdown::escape(&launch.name));
+    let text = if query.trim().is_empty() {
+        format!(
+            "{}: `/follow <id|slug>` {} `/unfollow <id|slug>`",
+            markdown::escape(tr.usage),
+            markdown::escape(tr.or)
+        )
+    } else {
+        match sorted_launches(db) {
+            Ok(launches) => match find_launch(&launches, query) {
+                Some(launch) if start => match follow(db, sub, launch.id) {
+                    Ok(_) => fill(tr.following_launch, &[&name(launch)]),
+                    Err(err) => error_text(tr.error_following_launch, err),
+                },
+                Some(launch)
+                    if db
+                        .get_settings(sub)
+                        .is_ok_and(|settings| settings.follows_launch(launch)) =>
+                {
+                    markdown::escape(tr.matched_launch_unfollow)
+                }
+                Some(launch) => match db.unfollow(sub, launch.id) {
+                    Ok(true) => fill(tr.not_following_launch, &[&name(launch)]),
+                    Ok(false) => markdown::escape(tr.launch_not_followed),
+                    Err(err) => error_text(tr.error_unfollowing_launch, err),
+                },
+                None => markdown::escape(tr.no_such_launch),
+            },
+            Err(err) => error_text(tr.error_getting_launches, err),
+        }
+    };
+    reply(bot, msg, text).await?;
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use chrono::{DateTime, Utc};
+use teloxide::{
+    prelude::*,
+    types::{Message, MessageId},
+    utils::markdown,
+    ApiError, RequestError,
+};
+use tracing::{info, warn};
+
+use super::{
+    error_text, handle_send_error, msg_lang, msg_subscriber, reply, send_to, update_settings, MyBot,
+};
+use crate::{
+    db::{ChatSettings, Db, DbResult, Subscriber},
+    types::{Launch, RLError},
+};
+
+/// Soonest launch after `now` the subscription would be notified about.
+fn next_launch<'a>(
+    launches: &'a [Launch],
+    settings: &ChatSettings,
+    now: DateTime<Utc>,
+) -> Option<&'a Launch> {
+    launches
+        .iter()
+        .filter(|l| l.t0.is_some_and(|t0| t0 > now))
+        .filter(|l| !settings.filters_out(l) && !settings.muted_launches.contains(&l.id))
+        .min_by_key(|l| l.t0)
+}
+
+/// Pinned message text, without countdown so it only changes with the feed
+/// or chat settings.
+fn pinned_text(launch: Option<&Launch>, settings: &ChatSettings) -> String {
+    let tr = settings.language.unwrap_or_default().catalog();
+    let Some((launch, t0)) = launch.and_then(|l| Some((l, l.t0?))) else {
+        return markdown::escape(tr.no_upcoming_launches);
+    };
+    format!(
+        "*{}*\n[{} \\- {}](https://rocketlaunch.live/launch/{}) {}\n{}\n{}",
+        markdown::escape(tr.next_launch),
+        markdown::escape(&launch.provider.name),
+        markdown::escape(&launch.vehicle.name),
+        markdown::escape(&launch.slug),
+        markdown::escape(&launch.name),
+        markdown::escape(
+            &t0.with_timezone(&settings.timezone)
+                .format("%Y-%m-%d %H:%M %Z")
+                .to_string()
+        ),
+        markdown::escape(&launch.pad.to_string()),
+    )
+}
+
+/// Brings pinned message of `sub` up to date with `launches`: edits it when
+/// the next launch details change, and posts and pins a new one when another
+/// launch becomes the next. Missing pin rights don't count as errors, the
+/// message is kept up to date anyway.
+pub(super) async fn update_pin(
+    bot: &MyBot,
+    db: &Db,
+    sub: Subscriber,
+    launches: &[Launch],
+) -> Result<(), RLError> {
+    let settings = db.get_settings(sub)?;
+    let mut info = db.get_subscriber_info(sub)?;
+    let launch = next_launch(launches, &settings, Utc::now());
+    let text = pinned_text(launch, &settings);
+    let launch_id = launch.map(|l| l.id);
+
+    if let Some(message_id) = info.pinned_message {
+        if info.pinned_launch == launch_id {
+            if info.pinned_text.as_ref() == Some(&text) {
+                return Ok(());
+            }
+            let edited = bot
+                .edit_message_text(ChatId(sub.chat_id), MessageId(message_id), &text)
+                .disable_web_page_preview(true)
+                .await;
+            match edited {
+                Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => {
+                    info.pinned_text = Some(text);
+                    db.set_subscriber_info(sub, &info)?;
+                    return Ok(());
+                }
+                // deleted by someone, post a new one
+                Err(RequestError::Api(ApiError::MessageToEditNotFound)) => {}
+                Err(err) => {
+                    handle_send_error(db, sub, &err)?;
+                    Err(err)?
+                }
+            }
+        }
+        if let Err(err) = bot
+            .unpin_chat_message(ChatId(sub.chat_id))
+            .message_id(MessageId(message_id))
+            .await
+        {
+            warn!("failed unpinning message in {}: {}", sub, err);
+        }
+    }
+
+    info!("pinning next launch {:?} in {}", launch_id, sub);
+    let message = match send_to(bot, sub, &text)
+        .disable_web_page_preview(true)
+        .disable_notification(true)
+        .await
+    {
+        Ok(message) => message,
+        Err(err) => {
+            handle_send_error(db, sub, &err)?;
+            Err(err)?
+        }
+    };
+    if let Err(err) = bot
+        .pin_chat_message(ChatId(sub.chat_id), message.id)
+        .disable_notification(true)
+        .await
+    {
+        warn!("failed pinning message in {}: {}", sub, err);
+    }
+    info.pinned_message = Some(message.id.0);
+    info.pinned_launch = launch_id;
+    info.pinned_text = Some(text);
+    db.set_subscriber_info(sub, &info)?;
+    Ok(())
+}
+
+/// Unpins and forgets the message, keeping it in chat history.
+pub(super) async fn remove_pin(bot: &MyBot, db: &Db, sub: Subscriber) -> DbResult<()> {
+    let mut info = db.get_subscriber_info(sub)?;
+    let Some(message_id) = info.pinned_message.take() else {
+        return Ok(());
+    };
+    if let Err(err) = bot
+        .unpin_chat_message(ChatId(sub.chat_id))
+        .message_id(MessageId(message_id))
+        .await
+    {
+        warn!("failed unpinning message in {}: {}", sub, err);
+    }
+    info.pinned_launch = None;
+    info.pinned_text = None;
+    db.set_subscriber_info(sub, &info)
+}
+
+/// `/pin_next on|off`
+pub(super) async fn pin_command(
+    bot: &MyBot,
+    db: &Db,
+    msg: &Message,
+    args: &str,
+) -> ResponseResult<()> {
+    let tr = msg_lang(db, msg).catalog();
+    let sub = msg_subscriber(msg);
+    let pin = match args.trim() {
+        "on" => true,
+        "off" => false,
+        _ => {
+            let usage = format!("{}: `/pin_next on|off`", markdown::escape(tr.usage));
+            reply(bot, msg, usage).await?;
+            return Ok(());
+        }
+    };
+    let subscribed = db.is_subscribed(sub).unwrap_or(false);
+    let text = match update_settings(db, sub, |settings| settings.pin_next = pin) {
+        Ok(()) if !pin => match remove_pin(bot, db, sub).await {
+            Ok(()) => markdown::escape(tr.pin_off),
+            Err(err) => error_text(tr.error_saving_settings, err),
+        },
+        Ok(()) if !subscribed => markdown::escape(tr.pin_on_unsubscribed),
+        Ok(()) => {
+            let launches = db.get_launches().unwrap_or_default();
+            match update_pin(bot, db, sub, &launches).await {
+                Ok(()) => markdown::escape(tr.pin_on),
+                Err(err) => error_text(tr.error_pinning, err),
+            }
+        }
+        Err(err) => error_text(tr.error_saving_settings, err),
+    };
+    reply(bot, msg, text).await?;
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use humantime::format_duration;
+use teloxide::{prelude::*, types::Message, utils::markdown};
+
+use super::{error_text, fill, msg_lang, msg_subscriber, reply, update_settings, MyBot};
+use crate::{
+    db::{Db, QuietHours, QuietMode},
+    i18n::Lang,
+};
+
+fn quiet_hours_usage(lang: Lang) -> String {
+    let tr = lang.catalog();
+    format!(
+        "{}: `/quiet_hours <HH:MM\\-HH:MM> <delay|silent|drop>`, `/quiet_hours off`\n\n{}",
+        markdown::escape(tr.usage),
+        markdown::escape(tr.quiet_hours_help)
+    )
+}
+
+/// `23:00-07:00 silent`
+fn parse_quiet_hours(args: &str) -> Option<QuietHours> {
+    let (range, mode) = args.split_once(char::is_whitespace)?;
+    let (start, end) = range.split_once('-')?;
+    let time = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").ok();
+    let quiet = QuietHours {
+        start: time(start)?,
+        end: time(end)?,
+        mode: QuietMode::parse(mode.trim())?,
+    };
+    (quiet.start != quiet.end).then_some(quiet)
+}
+
+pub(super) fn quiet_hours_text(quiet: &QuietHours) -> String {
+    markdown::escape(&format!(
+        "{}-{} {}",
+        quiet.start.format("%H:%M"),
+        quiet.end.format("%H:%M"),
+        quiet.mode.as_str()
+    ))
+}
+
+/// `/timezone <name>`, timezone names as in the tz database.
+pub(super) async fn timezone_command(
+    bot: &MyBot,
+    db: &Db,
+    msg: &Message,
+    name: &str,
+) -> ResponseResult<()> {
+    let tr = msg_lang(db, msg).catalog();
+    let sub = msg_subscriber(msg);
+    let name = name.trim();
+    let code = |timezone: Tz| format!("`{}`", markdown::escape_code(timezone.name()));
+    let text = if name.is_empty() {
+        let timezone = db.get_settings(sub).unwrap_or_default().timezone;
+        format!(
+            "{}\n\n{}",
+            fill(tr.timezone_is, &[&code(timezone)]),
+            fill(
+                tr.timezone_usage,
+                &["`/timezone <name>`", "`/timezone Europe/Berlin`"]
+            )
+        )
+    } else {
+        match name.parse::<Tz>() {
+            Ok(timezone) => {
+                let saved = update_settings(db, sub, |settings| settings.timezone = timezone);
+                match saved {
+                    Ok(()) => fill(tr.timezone_set, &[&code(timezone)]),
+                    Err(err) => error_text(tr.error_saving_settings, err),
+                }
+            }
+            Err(_) => markdown::escape(tr.timezone_unknown),
+        }
+    };
+    reply(bot, msg, text).await?;
+    Ok(())
+}
+
+/// `/quiet_hours <start-end> <mode>` and `/quiet_hours off`.
+pub(super) async fn quiet_hours_command(
+    bot: &MyBot,
+    db: &Db,
+    msg: &Message,
+    args: &str,
+) -> ResponseResult<()> {
+    let lang = msg_lang(db, msg);
+    let tr = lang.catalog();
+    let sub = msg_subscriber(msg);
+    let args = args.trim();
+    let quiet = match args {
+        "off" => None,
+        _ => match parse_quiet_hours(args) {
+            Some(quiet) => Some(quiet),
+            None => {
+                reply(bot, msg, quiet_hours_usage(lang)).await?;
+                return Ok(());
+            }
+        },
+    };
+    let text = match update_settings(db, sub, |settings| settings.quiet_hours = quiet) {
+        Ok(()) => match &quiet {
+            Some(quiet) => fill(tr.quiet_hours_set, &[&quiet_hours_text(quiet)]),
+            None => markdown::escape(tr.quiet_hours_off),
+        },
+        Err(err) => error_text(tr.error_saving_settings, err),
+    };
+    reply(bot, msg, text).await?;
+    Ok(())
+}
+
+/// `/reminder_sound <lead time> <loud|silent>`, lead time as in `24h`.
+pub(super) async fn reminder_sound_command(
+    bot: &MyBot,
+    db: &Db,
+    msg: &Message,
+    args: &str,
+) -> ResponseResult<()> {
+    let tr = msg_lang(db, msg).catalog();
+    let sub = msg_subscriber(msg);
+    let settings = db.get_settings(sub).unwrap_or_default();
+    let parsed = args
+        .trim()
+        .split_once(char::is_whitespace)
+        .and_then(|(time, sound)| {
+            let time = humantime::parse_duration(time).ok()?.as_secs() as i64;
+            let silent = match sound.trim() {
+                "loud" => false,
+                "silent" => true,
+                _ => return None,
+            };
+            Some((time, silent))
+        });
+    let text = match parsed {
+        Some((time, silent)) if settings.notify_times.contains(&time) => {
+            let saved = update_settings(db, sub, |settings| {
+                settings.silent_times.retain(|&t| t != time);
+                if silent {
+                    settings.silent_times.push(time);
+                }
+            });
+            let time = markdown::escape(&tr.duration(std::time::Duration::from_secs(time as u64)));
+            match saved {
+                Ok(()) if silent => fill(tr.reminders_silent, &[&time]),
+                Ok(()) => fill(tr.reminders_loud, &[&time]),
+                Err(err) => error_text(tr.error_saving_settings, err),
+            }
+        }
+        _ => format!(
+            "{}: `/reminder_sound <lead time> <loud|silent>`\n\n{}: {}",
+            markdown::escape(tr.usage),
+            markdown::escape(tr.lead_times),
+            settings
+                .notify_times
+                .iter()
+                .map(|&t| format!(
+                    "`{}`",
+                    format_duration(std::time::Duration::from_secs(t as u64))
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    reply(bot, msg, text).await?;
+    Ok(())
+}
+
+/// `1d, 1h, 15m`
+pub(super) fn lead_times_text(times: &[i64], lang: Lang) -> String {
+    times
+        .iter()
+        .map(|&t| {
+            lang.catalog()
+                .duration(std::time::Duration::from_secs(t as u64))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,252 @@
+use std::{fmt::Write, time::Duration};
+
+use chrono::{DateTime, Utc};
+use teloxide::utils::markdown;
+
+use super::fill;
+use crate::{i18n::Lang, types::Launch};
+
+/// Most launches listed by `/find`, to stay well under message size limit.
+const MAX_FOUND: usize = 30;
+
+/// Search query over cached launches, every term has to match. Terms are
+/// `provider:`, `vehicle:`, `country:`, `tag:` and `next:<duration>`, other
+/// words are looked up in provider, vehicle, mission and location.
+#[derive(Debug, Default)]
+pub(super) struct LaunchFilter {
+    providers: Vec<String>,
+    vehicles: Vec<String>,
+    countries: Vec<String>,
+    tags: Vec<String>,
+    words: Vec<String>,
+    within: Option<Duration>,
+}
+
+/// Bad term of a search query, with the part that's wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum FilterError {
+    EmptyValue(String),
+    UnknownFilter(String),
+    BadDuration(String),
+}
+
+impl FilterError {
+    fn text(&self, lang: Lang) -> String {
+        let tr = lang.catalog();
+        let (text, term) = match self {
+            FilterError::EmptyValue(key) => (tr.filter_empty_value, key),
+            FilterError::UnknownFilter(key) => (tr.filter_unknown, key),
+            FilterError::BadDuration(value) => (tr.filter_bad_duration, value),
+        };
+        fill(text, &[&format!("`{}`", markdown::escape_code(term))])
+    }
+}
+
+impl LaunchFilter {
+    pub(super) fn parse(query: &str) -> Result<Self, FilterError> {
+        let mut filter = Self::default();
+        for term in query.split_whitespace() {
+            let Some((key, value)) = term.split_once(':') else {
+                filter.words.push(term.to_lowercase());
+                continue;
+            };
+            if value.is_empty() {
+                return Err(FilterError::EmptyValue(key.to_string()));
+            }
+            let value = value.to_lowercase();
+            match key.to_lowercase().as_str() {
+                "provider" => filter.providers.push(value),
+                "vehicle" => filter.vehicles.push(value),
+                "country" => filter.countries.push(value),
+                "tag" => filter.tags.push(value),
+                "next" => {
+                    let within = humantime::parse_duration(&value)
+                        .map_err(|_| FilterError::BadDuration(value.clone()))?;
+                    filter.within = Some(within);
+                }
+                _ => return Err(FilterError::UnknownFilter(key.to_string())),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Launches without T-0 only match when there's no `next:` term.
+    pub(super) fn matches(&self, launch: &Launch, now: DateTime<Utc>) -> bool {
+        match (launch.t0, self.within) {
+            (Some(t0), _) if t0 < now => return false,
+            (Some(t0), Some(within)) if (t0 - now).to_std().is_ok_and(|d| d > within) => {
+                return false;
+            }
+            (None, Some(_)) => return false,
+            _ => {}
+        }
+        let all_found = |values: &[String], fields: &[&str]| {
+            values.iter().all(|value| {
+                fields
+                    .iter()
+                    .any(|field| field.to_lowercase().contains(value))
+            })
+        };
+        all_found(
+            &self.providers,
+            &[&launch.provider.name, &launch.provider.slug],
+        ) && all_found(
+            &self.vehicles,
+            &[&launch.vehicle.name, &launch.vehicle.slug],
+        ) && self
+            .countries
+            .iter()
+            .all(|country| country_matches(&launch.pad.location.country, country))
+            && self.tags.iter().all(|tag| {
+                launch
+                    .tags
+                    .iter()
+                    .any(|t| t.text.to_lowercase().contains(tag))
+            })
+            && self.words_match(launch)
+    }
+
+    fn words_match(&self, launch: &Launch) -> bool {
+        let mut haystack = vec![
+            launch.name.as_str(),
+            launch.provider.name.as_str(),
+            launch.provider.slug.as_str(),
+            launch.vehicle.name.as_str(),
+            launch.vehicle.slug.as_str(),
+            launch.pad.name.as_str(),
+            launch.pad.location.name.as_str(),
+            launch.pad.location.country.as_str(),
+        ];
+        haystack.extend(launch.pad.location.state_name.as_deref());
+        haystack.extend(launch.missions.iter().map(|m| m.name.as_str()));
+        let haystack = haystack.join("\n").to_lowercase();
+        self.words.iter().all(|word| haystack.contains(word))
+    }
+}
+
+/// Api has full country names only, so `us` matches `United States` by
+/// initials. Otherwise the query has to be whole words of the name, as in
+/// `korea` or `new_zealand`, so `us` doesn't match `Russia`.
+fn country_matches(country: &str, query: &str) -> bool {
+    let split = |s: &str| {
+        s.to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    let words = split(country);
+    let query = split(query);
+    if query.is_empty() {
+        return false;
+    }
+    if words.windows(query.len()).any(|window| window == query) {
+        return true;
+    }
+    words.len() > 1
+        && query.len() == 1
+        && words
+            .iter()
+            .filter_map(|word| word.chars().next())
+            .collect::<String>()
+            == query[0]
+}
+
+/// One line per launch matching `query`, soonest first.
+pub(super) fn find_text(launches: &[Launch], query: &str, lang: Lang) -> String {
+    let tr = lang.catalog();
+    let filter = match LaunchFilter::parse(query) {
+        Ok(filter) => filter,
+        Err(err) => {
+            return format!(
+                "{}\n\n{}: `/find [provider:…] [vehicle:…] [country:…] [tag:…] [next:7d] [words]`",
+                err.text(lang),
+                markdown::escape(tr.usage)
+            );
+        }
+    };
+    let now = Utc::now();
+    let mut found = launches
+        .iter()
+        .filter(|l| filter.matches(l, now))
+        .collect::<Vec<_>>();
+    found.sort_by_key(|l| l.sort_date);
+    if found.is_empty() {
+        return markdown::escape(tr.no_launches_found);
+    }
+
+    let mut text = format!("*{}*", fill(tr.found, &[&found.len().to_string()]));
+    for launch in found.iter().take(MAX_FOUND) {
+        let date = match launch.t0 {
+            Some(t0) => t0.format("%Y-%m-%d %H:%M").to_string(),
+            None => launch.date_str.clone(),
+        };
+        let _ = write!(
+            text,
+            "\n`{}` [{} \\- {}](https://rocketlaunch.live/launch/{}) {}",
+            markdown::escape_code(&date),
+            markdown::escape(&launch.provider.name),
+            markdown::escape(&launch.vehicle.name),
+            markdown::escape(&launch.slug),
+            markdown::escape(&launch.name),
+        );
+    }
+    if found.len() > MAX_FOUND {
+        let more = (found.len() - MAX_FOUND).to_string();
+        let _ = write!(text, "\n{}", fill(tr.and_more, &[&more]));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_terms() {
+        let filter = LaunchFilter::parse("Provider:SpaceX  tag:Crew starlink next:7d").unwrap();
+        assert_eq!(filter.providers, ["spacex"]);
+        assert_eq!(filter.tags, ["crew"]);
+        assert_eq!(filter.words, ["starlink"]);
+        assert_eq!(filter.within, Some(Duration::from_secs(7 * 86400)));
+        assert!(filter.vehicles.is_empty() && filter.countries.is_empty());
+
+        let filter = LaunchFilter::parse("vehicle:falcon vehicle:heavy country:us").unwrap();
+        assert_eq!(filter.vehicles, ["falcon", "heavy"]);
+        assert_eq!(filter.countries, ["us"]);
+        assert_eq!(filter.within, None);
+
+        assert!(LaunchFilter::parse("").unwrap().words.is_empty());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            LaunchFilter::parse("spacex provider:").unwrap_err(),
+            FilterError::EmptyValue("provider".to_string())
+        );
+        assert_eq!(
+            LaunchFilter::parse("Pad:39A").unwrap_err(),
+            FilterError::UnknownFilter("Pad".to_string())
+        );
+        assert_eq!(
+            LaunchFilter::parse("next:soon").unwrap_err(),
+            FilterError::BadDuration("soon".to_string())
+        );
+    }
+
+    #[test]
+    fn countries() {
+        assert!(country_matches("United States", "us"));
+        assert!(country_matches("United States", "united_states"));
+        assert!(country_matches("United States", "States"));
+        assert!(!country_matches("Russia", "us"));
+        assert!(!country_matches("Australia", "us"));
+        assert!(country_matches("New Zealand", "new_zealand"));
+        assert!(country_matches("New Zealand", "zealand"));
+        assert!(!country_matches("New Zealand", "zeal"));
+        assert!(country_matches("South Korea", "korea"));
+        assert!(!country_matches("Kazakhstan", "k"));
+        assert!(!country_matches("China", "_"));
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::Write;
+
+use teloxide::{
+    prelude::*,
+    types::{Chat, Message, User},
+    utils::markdown,
+};
+
+use super::{
+    digest::digest_schedule_text,
+    msg_subscriber,
+    quiet::{lead_times_text, quiet_hours_text},
+    MyBot,
+};
+use crate::{
+    db::{Db, DbResult, Subscriber},
+    i18n::Lang,
+};
+
+/// Whether sender of `msg` may change subscription and settings of the chat:
+/// anyone in private chats, group administrators in groups, unless the group
+/// allows everyone. Forum topics follow the setting of the whole group.
+pub(super) async fn can_manage(bot: &MyBot, db: &Db, msg: &Message) -> ResponseResult<bool> {
+    if msg.chat.is_private() || everyone_can_manage(db, &msg.chat) {
+        return Ok(true);
+    }
+    is_chat_admin(bot, msg).await
+}
+
+/// Same as [`can_manage`], for `user` pressing a button under a message in
+/// `chat`.
+pub(super) async fn user_can_manage(
+    bot: &MyBot,
+    db: &Db,
+    chat: &Chat,
+    user: &User,
+) -> ResponseResult<bool> {
+    if chat.is_private() || everyone_can_manage(db, chat) {
+        return Ok(true);
+    }
+    let member = bot.get_chat_member(chat.id, user.id).await?;
+    Ok(member.is_privileged())
+}
+
+fn everyone_can_manage(db: &Db, chat: &Chat) -> bool {
+    db.get_settings(Subscriber::chat(chat.id.0))
+        .is_ok_and(|settings| settings.everyone_can_manage)
+}
+
+/// Whether sender of `msg` is an administrator of the chat it's sent to.
+pub(super) async fn is_chat_admin(bot: &MyBot, msg: &Message) -> ResponseResult<bool> {
+    if msg.chat.is_private() {
+        return Ok(true);
+    }
+    // anonymous group administrators send messages on behalf of the group
+    if msg.sender_chat().is_some_and(|chat| chat.id == msg.chat.id) {
+        return Ok(true);
+    }
+    let Some(user) = msg.from() else {
+        return Ok(false);
+    };
+    let member = bot.get_chat_member(msg.chat.id, user.id).await?;
+    Ok(member.is_privileged())
+}
+
+pub(super) fn settings_text(db: &Db, msg: &Message, lang: Lang) -> DbResult<String> {
+    let tr = lang.catalog();
+    let sub = msg_subscriber(msg);
+    let settings = db.get_settings(sub)?;
+    let subscribed = db.is_subscribed(sub)?;
+
+    let mut text = format!(
+        "*{}*\n{}: {}\n{}: {}",
+        markdown::escape(if sub.thread_id.is_some() {
+            tr.topic_settings
+        } else {
+            tr.settings
+        }),
+        markdown::escape(tr.subscribed_label),
+        markdown::escape(if subscribed { tr.yes } else { tr.no }),
+        markdown::escape(tr.notify_before),
+        markdown::escape(&lead_times_text(&settings.notify_times, lang)),
+    );
+    let mut line = |label: &str, value: String| {
+        let _ = write!(text, "\n{}: {}", markdown::escape(label), value);
+    };
+    if !settings.silent_times.is_empty() {
+        line(
+            tr.silent_before,
+            markdown::escape(&lead_times_text(&settings.silent_times, lang)),
+        );
+    }
+    if let Some(quiet) = &settings.quiet_hours {
+        line(
+            tr.quiet_hours,
+            format!(
+                "{} `{}`",
+                quiet_hours_text(quiet),
+                markdown::escape_code(settings.timezone.name())
+            ),
+        );
+    }
+    if let Some(lang) = settings.language {
+        line(tr.language, markdown::escape(lang.catalog().name));
+    }
+    if let Some(digest) = &settings.digest {
+        line(
+            tr.digest,
+            format!(
+                "{} `{}`",
+                digest_schedule_text(digest, lang),
+                markdown::escape_code(settings.timezone.name())
+            ),
+        );
+    }
+    if settings.pin_next {
+        line(tr.next_launch_label, markdown::escape(tr.pinned));
+    }
+    if settings.exclude_suborbital {
+        line(tr.suborbital_launches, markdown::escape(tr.excluded));
+    }
+    let ids = |ids: &[u64]| {
+        ids.iter()
+            .map(|id| format!("`{}`", id))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let codes = |codes: &[String]| {
+        codes
+            .iter()
+            .map(|code| format!("`{}`", markdown::escape_code(code)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let follows = db.get_follows(sub)?;
+    if !follows.is_empty() {
+        let mut followed = follows.into_keys().collect::<Vec<_>>();
+        followed.sort_unstable();
+        line(tr.followed_launches, ids(&followed));
+    }
+    if !settings.vehicles.is_empty() {
+        line(tr.followed_vehicles, codes(&settings.vehicles));
+    }
+    if !settings.tags.is_empty() {
+        line(tr.followed_tags, codes(&settings.tags));
+    }
+    if !settings.muted_tags.is_empty() {
+        line(tr.muted_tags, codes(&settings.muted_tags));
+    }
+    if !settings.muted_launches.is_empty() {
+        line(tr.muted_launches, ids(&settings.muted_launches));
+    }
+    if !msg.chat.is_private() {
+        let everyone = db
+            .get_settings(Subscriber::chat(sub.chat_id))?
+            .everyone_can_manage;
+        line(
+            tr.managed_by,
+            markdown::escape(if everyone {
+                tr.managed_by_everyone
+            } else {
+                tr.managed_by_admins
+            }),
+        );
+    }
+    Ok(text)
+}
@@ -0,0 +1,103 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use teloxide::{prelude::*, types::Message, utils::markdown};
+
+use super::{error_text, fill, msg_lang, msg_subscriber, reply, update_settings, MyBot};
+use crate::{
+    db::{Db, DbResult},
+    i18n::Lang,
+    types::Launch,
+};
+
+fn usage(lang: Lang) -> String {
+    format!(
+        "{}: `/tag follow|unfollow|mute|unmute <tag>`",
+        markdown::escape(lang.catalog().usage)
+    )
+}
+
+/// Lowercase tag to its text and number of cached launches having it.
+fn known_tags(launches: &[Launch]) -> BTreeMap<String, (&str, usize)> {
+    let mut tags = BTreeMap::new();
+    for tag in launches.iter().flat_map(|l| &l.tags) {
+        tags.entry(tag.text.to_lowercase())
+            .or_insert((tag.text.as_str(), 0))
+            .1 += 1;
+    }
+    tags
+}
+
+/// `/tags`, every tag of cached launches.
+pub(super) fn tags_text(launches: &[Launch], lang: Lang) -> String {
+    let tr = lang.catalog();
+    let tags = known_tags(launches);
+    if tags.is_empty() {
+        return markdown::escape(tr.no_tags);
+    }
+    let mut text = format!("*{}*", markdown::escape(tr.tags_title));
+    for (name, count) in tags.values() {
+        let _ = write!(
+            text,
+            "\n`{}` {}",
+            markdown::escape_code(name),
+            markdown::escape(&format!("({})", count))
+        );
+    }
+    let _ = write!(text, "\n\n{}", usage(lang));
+    text
+}
+
+fn update_tags(db: &Db, msg: &Message, action: &str, tag: &str) -> DbResult<()> {
+    let sub = msg_subscriber(msg);
+    let tag = tag.to_string();
+    let change = |list: &mut Vec<String>, add: bool| {
+        list.retain(|t| *t != tag);
+        if add {
+            list.push(tag.clone());
+        }
+    };
+    update_settings(db, sub, |settings| match action {
+        "follow" => change(&mut settings.tags, true),
+        "unfollow" => change(&mut settings.tags, false),
+        "mute" => change(&mut settings.muted_tags, true),
+        _ => change(&mut settings.muted_tags, false),
+    })?;
+    let launches = db.get_launches()?;
+    match action {
+        "follow" => db.follow_matching_launches(&launches)?,
+        "unfollow" => db.unfollow_unmatched(sub, &launches)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `/tag follow|unfollow|mute|unmute <tag>`, tags are matched ignoring case.
+pub(super) async fn tag_command(
+    bot: &MyBot,
+    db: &Db,
+    msg: &Message,
+    args: &str,
+) -> ResponseResult<()> {
+    let lang = msg_lang(db, msg);
+    let tr = lang.catalog();
+    let (action, tag) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+    let tag = tag.trim().to_lowercase();
+    let text = if tag.is_empty() || !matches!(action, "follow" | "unfollow" | "mute" | "unmute") {
+        usage(lang)
+    } else {
+        match update_tags(db, msg, action, &tag) {
+            Ok(()) => {
+                let done = match action {
+                    "follow" => tr.tag_followed,
+                    "unfollow" => tr.tag_unfollowed,
+                    "mute" => tr.tag_muted,
+                    _ => tr.tag_unmuted,
+                };
+                fill(done, &[&format!("`{}`", markdown::escape_code(&tag))])
+            }
+            Err(err) => error_text(tr.error_saving_settings, err),
+        }
+    };
+    reply(bot, msg, text).await?;
+    Ok(())
+}
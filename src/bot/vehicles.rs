@@ -0,0 +1,98 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use teloxide::{prelude::*, types::Message, utils::markdown};
+
+use super::{error_text, fill, msg_lang, msg_subscriber, reply, update_settings, MyBot};
+use crate::{
+    db::{Db, DbResult},
+    i18n::Lang,
+    types::Launch,
+};
+
+/// Vehicle slug to name, of every cached launch.
+fn known_vehicles(launches: &[Launch]) -> BTreeMap<&str, &str> {
+    launches
+        .iter()
+        .map(|l| (l.vehicle.slug.as_str(), l.vehicle.name.as_str()))
+        .collect()
+}
+
+/// Slug of vehicle `query` names, looked up by slug or name in cached launches.
+/// Unknown slugs are accepted too, vehicle may have no launches scheduled yet.
+fn resolve_vehicle(launches: &[Launch], query: &str) -> Option<String> {
+    let query = query.trim();
+    let known = known_vehicles(launches);
+    let found = known
+        .iter()
+        .find(|(slug, name)| slug.eq_ignore_ascii_case(query) || name.eq_ignore_ascii_case(query));
+    if let Some((slug, _)) = found {
+        return Some(slug.to_string());
+    }
+    let is_slug = !query.is_empty() && query.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    is_slug.then(|| query.to_lowercase())
+}
+
+fn vehicles_text(launches: &[Launch], lang: Lang) -> String {
+    let tr = lang.catalog();
+    let mut text = format!(
+        "{}: `/follow_vehicle <slug|name>`, `/unfollow_vehicle <slug|name>`\n\n*{}*",
+        markdown::escape(tr.usage),
+        markdown::escape(tr.vehicles_title)
+    );
+    for (slug, name) in known_vehicles(launches) {
+        let _ = write!(
+            text,
+            "\n`{}` {}",
+            markdown::escape_code(slug),
+            markdown::escape(name)
+        );
+    }
+    text
+}
+
+fn follow_vehicle(db: &Db, msg: &Message, slug: &str, start: bool) -> DbResult<()> {
+    let sub = msg_subscriber(msg);
+    update_settings(db, sub, |settings| {
+        settings.vehicles.retain(|s| s != slug);
+        if start {
+            settings.vehicles.push(slug.to_string());
+        }
+    })?;
+    let launches = db.get_launches()?;
+    if start {
+        db.follow_matching_launches(&launches)
+    } else {
+        db.unfollow_unmatched(sub, &launches)
+    }
+}
+
+/// `/follow_vehicle <slug|name>` and `/unfollow_vehicle <slug|name>`, listing
+/// known vehicles without arguments.
+pub(super) async fn vehicle_command(
+    bot: &MyBot,
+    db: &Db,
+    msg: &Message,
+    query: &str,
+    start: bool,
+) -> ResponseResult<()> {
+    let lang = msg_lang(db, msg);
+    let tr = lang.catalog();
+    let launches = db.get_launches().unwrap_or_default();
+    let text = if query.trim().is_empty() {
+        vehicles_text(&launches, lang)
+    } else {
+        match resolve_vehicle(&launches, query) {
+            Some(slug) => {
+                let code = format!("`{}`", markdown::escape_code(&slug));
+                match follow_vehicle(db, msg, &slug, start) {
+                    Ok(()) if start => fill(tr.vehicle_followed, &[&code]),
+                    Ok(()) => fill(tr.vehicle_unfollowed, &[&code]),
+                    Err(err) => error_text(tr.error_saving_settings, err),
+                }
+            }
+            None => markdown::escape(tr.not_a_vehicle),
+        }
+    };
+    reply(bot, msg, text).await?;
+    Ok(())
+}
@@ -1,22 +1,54 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use serde::Deserialize;
 
+use crate::template::Template;
+
 #[derive(Debug, Clone, Parser)]
 pub struct Args {
     pub config: PathBuf,
+
+    /// overrides `data_dir` from config
+    #[arg(long)]
+    pub data_dir: Option<PathBuf>,
+
+    /// overrides `db_path` from config
+    #[arg(long)]
+    pub db_path: Option<PathBuf>,
 }
 
 impl Args {
     pub fn get_config(self) -> Config {
-        Config::parse_file(self.config)
+        let mut config = Config::parse_file(self.config);
+        if let Some(data_dir) = self.data_dir {
+            config.data_dir = data_dir;
+        }
+        if let Some(db_path) = self.db_path {
+            config.db_path = Some(db_path);
+        }
+        config
     }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub bot: BotConfig,
+
+    #[serde(default)]
+    pub storage: StorageBackend,
+
+    /// Directory for bot data, relative `db_path` is resolved against it.
+    #[serde(default = "default_data_dir")]
+    pub data_dir: PathBuf,
+
+    /// Database path, defaults to [`StorageBackend::default_path`].
+    #[serde(default)]
+    pub db_path: Option<PathBuf>,
+}
+
+fn default_data_dir() -> PathBuf {
+    PathBuf::from(".")
 }
 
 #[derive(Debug, Clone, Deserialize, Parser)]
@@ -24,11 +56,45 @@ pub struct BotConfig {
     #[arg(long, value_parser)]
     pub token: String,
 
+    /// Owner chats, can add and remove other admins at runtime. Owners removed
+    /// from here lose admin access on the next start.
     #[arg(long, value_parser)]
     pub admin_chats: Vec<i64>,
+
+    /// Notification format, replaces one set by admins on start, see
+    /// [`Template`].
+    #[arg(long, value_parser = Template::parse)]
+    #[serde(default)]
+    pub notification_template: Option<Template>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Sled,
+    Sqlite,
+}
+
+impl StorageBackend {
+    /// Database path used when none is configured.
+    pub fn default_path(self) -> &'static str {
+        match self {
+            StorageBackend::Sled => "db",
+            StorageBackend::Sqlite => "db.sqlite",
+        }
+    }
 }
 
 impl Config {
+    pub fn db_path(&self) -> PathBuf {
+        let db_path = self
+            .db_path
+            .clone()
+            .unwrap_or_else(|| self.storage.default_path().into());
+        self.data_dir.join(db_path)
+    }
+
     pub fn parse_file(path: PathBuf) -> Self {
         let yaml_content = std::fs::read_to_string(path).expect("Failed to read config file");
         serde_yaml::from_str(&yaml_content).expect("Failed parsing config")
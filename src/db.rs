@@ -1,143 +1,1201 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    num::ParseIntError,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use tracing::{debug, info};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use fs2::FileExt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tracing::{debug, info, warn};
 
-use crate::types::Launch;
+use crate::{config::StorageBackend, i18n::Lang, types::Launch};
 
-const NOTIFY_TIMES: [i64; 3] = [3600 * 24, 3600, 15 * 60];
+mod sled_storage;
+mod sqlite_storage;
 
-#[derive(Debug, Clone)]
-pub struct Db {
-    db: sled::Db,
+pub use sled_storage::SledStorage;
+pub use sqlite_storage::SqliteStorage;
+
+pub const NOTIFY_TIMES: [i64; 3] = [3600 * 24, 3600, 15 * 60];
+
+const LAST_FETCH_KEY: &str = "last_fetch";
+const NOTIFICATION_TEMPLATE_KEY: &str = "notification_template";
+const PENDING_BROADCAST_KEY_PREFIX: &str = "pending_broadcast:";
+
+pub type Db = Arc<dyn Storage>;
+pub type DbResult<T> = Result<T, DbError>;
+/// Followed launch id to its follow.
+pub type Follows = HashMap<u64, Follow>;
+
+/// Launch followed by a chat regardless of subscription.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StoredFollow")]
+pub struct Follow {
+    /// Seconds left until T-0 at the moment of the last notification, if
+    /// there was one.
+    pub time_diff: Option<i64>,
+    /// Followed for a vehicle or tag rather than with `/follow`, so it goes
+    /// away once they're unfollowed.
+    pub matched: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+/// Follows used to be stored as just their `time_diff`.
+#[derive(Deserialize)]
 #[serde(untagged)]
-enum Notified {
-    Data(i64, i64),
+enum StoredFollow {
+    TimeDiff(Option<i64>),
+    Follow {
+        time_diff: Option<i64>,
+        matched: bool,
+    },
 }
 
-impl Db {
-    pub fn open<P>(path: P) -> sled::Result<Self>
-    where
-        P: AsRef<Path>,
-    {
-        let db = sled::open(path)?;
-        Ok(Self { db })
+impl From<StoredFollow> for Follow {
+    fn from(stored: StoredFollow) -> Self {
+        match stored {
+            StoredFollow::TimeDiff(time_diff) => Self {
+                time_diff,
+                matched: false,
+            },
+            StoredFollow::Follow { time_diff, matched } => Self { time_diff, matched },
+        }
     }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DbError {
+    #[error("sled -> {0}")]
+    Sled(#[from] sled::Error),
+
+    #[error("sqlite -> {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("json -> {0}")]
+    Json(#[from] serde_json::Error),
 
-    pub fn subscribers_count(&self) -> sled::Result<usize> {
-        Ok(self.db.iter().count())
+    #[error("io -> {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("database {} is locked by another process", .0.display())]
+    Locked(PathBuf),
+}
+
+/// Where notifications go: a whole chat, or a single forum topic of it.
+///
+/// Written as `<chat_id>` or `<chat_id>:<thread_id>` in keys and dumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Subscriber {
+    pub chat_id: i64,
+    /// Forum topic, `None` for the chat itself.
+    pub thread_id: Option<i32>,
+}
+
+impl Subscriber {
+    pub fn chat(chat_id: i64) -> Self {
+        Self {
+            chat_id,
+            thread_id: None,
+        }
     }
+}
 
-    pub fn set_launches(&self, launches: &[Launch]) -> sled::Result<()> {
-        self.db
-            .set_merge_operator(|_key, _old, new| Some(new.to_vec()));
-        self.db
-            .merge("launches", serde_json::to_vec(launches).unwrap())?;
-        Ok(())
+impl fmt::Display for Subscriber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.thread_id {
+            Some(thread_id) => write!(f, "{}:{}", self.chat_id, thread_id),
+            None => write!(f, "{}", self.chat_id),
+        }
     }
+}
+
+impl FromStr for Subscriber {
+    type Err = ParseIntError;
 
-    pub fn get_launches(&self) -> sled::Result<Vec<Launch>> {
-        let launches = self
-            .db
-            .get("launches")?
-            .and_then(|val| serde_json::from_slice::<Vec<Launch>>(&val).ok())
-            .unwrap_or_default();
-        Ok(launches)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((chat_id, thread_id)) => Ok(Self {
+                chat_id: chat_id.parse()?,
+                thread_id: Some(thread_id.parse()?),
+            }),
+            None => Ok(Self::chat(s.parse()?)),
+        }
     }
+}
 
-    #[tracing::instrument(skip_all)]
-    pub fn set_notified(
-        &self,
+impl Serialize for Subscriber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Subscriber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // dumps made before topics were supported have plain chat ids
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            ChatId(i64),
+            Str(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::ChatId(chat_id) => Ok(Self::chat(chat_id)),
+            Repr::Str(s) => s.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Per-subscriber preferences, stored separately from the subscription itself.
+/// Forum topics have their own, chat-wide ones belong to the chat itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatSettings {
+    /// Seconds before T-0 at which the chat gets notified.
+    pub notify_times: Vec<i64>,
+    /// Those of `notify_times` notifying without sound.
+    pub silent_times: Vec<i64>,
+    /// In groups, let any member change subscription and settings, not only
+    /// group administrators.
+    pub everyone_can_manage: bool,
+    /// Launches not to notify about, even followed ones.
+    pub muted_launches: Vec<u64>,
+    /// Slugs of vehicles whose every launch gets followed.
+    pub vehicles: Vec<String>,
+    /// Lowercase tags whose every launch gets followed.
+    pub tags: Vec<String>,
+    /// Lowercase tags of launches not to notify about, unless followed.
+    pub muted_tags: Vec<String>,
+    /// Leave suborbital launches out of notifications and listings.
+    pub exclude_suborbital: bool,
+    /// Timezone of quiet hours and digest time.
+    pub timezone: Tz,
+    pub quiet_hours: Option<QuietHours>,
+    /// Summary of upcoming launches sent on schedule, in place of
+    /// notifications about each of them. Followed launches still notify.
+    pub digest: Option<Digest>,
+    /// Keep a pinned message about the next launch.
+    pub pin_next: bool,
+    /// Chosen with `/language` or taken from Telegram app of whoever
+    /// subscribed, English if neither.
+    pub language: Option<Lang>,
+}
+
+/// Local time range notifications shouldn't wake the chat up, may wrap past
+/// midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub mode: QuietMode,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// What happens to notifications due in quiet hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuietMode {
+    /// Sent once quiet hours end, unless the launch is gone by then.
+    Delay,
+    /// Sent right away with notification sound disabled.
+    Silent,
+    /// Not sent at all.
+    Drop,
+}
+
+impl QuietMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuietMode::Delay => "delay",
+            QuietMode::Silent => "silent",
+            QuietMode::Drop => "drop",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "delay" => Some(QuietMode::Delay),
+            "silent" => Some(QuietMode::Silent),
+            "drop" => Some(QuietMode::Drop),
+            _ => None,
+        }
+    }
+}
+
+/// Digest schedule, at local `time` of the chat timezone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Digest {
+    pub period: DigestPeriod,
+    pub time: NaiveTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestPeriod {
+    Daily,
+    Weekly(Weekday),
+}
+
+impl Digest {
+    /// How far ahead launches are listed.
+    pub fn span(&self) -> Duration {
+        match self.period {
+            DigestPeriod::Daily => Duration::try_days(1).unwrap(),
+            DigestPeriod::Weekly(_) => Duration::try_weeks(1).unwrap(),
+        }
+    }
+
+    /// Latest scheduled time not after `now`.
+    pub fn last_due(&self, timezone: Tz, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.with_timezone(&timezone).date_naive();
+        let date = match self.period {
+            DigestPeriod::Daily => today,
+            DigestPeriod::Weekly(weekday) => {
+                today - Duration::try_days(today.weekday().days_since(weekday).into()).unwrap()
+            }
+        };
+        let at = |date: NaiveDate| {
+            let local = date.and_time(self.time);
+            // local times skipped by daylight saving change happen an hour later
+            timezone
+                .from_local_datetime(&local)
+                .earliest()
+                .or_else(|| {
+                    timezone
+                        .from_local_datetime(&(local + Duration::try_hours(1).unwrap()))
+                        .earliest()
+                })
+                .unwrap_or_else(|| timezone.from_utc_datetime(&local))
+                .with_timezone(&Utc)
+        };
+        let due = at(date);
+        if due <= now {
+            due
+        } else {
+            at(date - self.span())
+        }
+    }
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            notify_times: NOTIFY_TIMES.to_vec(),
+            silent_times: vec![],
+            everyone_can_manage: false,
+            muted_launches: vec![],
+            vehicles: vec![],
+            tags: vec![],
+            muted_tags: vec![],
+            exclude_suborbital: false,
+            timezone: Tz::UTC,
+            quiet_hours: None,
+            digest: None,
+            pin_next: false,
+            language: None,
+        }
+    }
+}
+
+impl ChatSettings {
+    /// Whether `launch` gets followed for its vehicle or tags.
+    pub fn follows_launch(&self, launch: &Launch) -> bool {
+        self.vehicles.contains(&launch.vehicle.slug)
+            || launch
+                .tags
+                .iter()
+                .any(|tag| self.tags.contains(&tag.text.to_lowercase()))
+    }
+
+    /// Whether `launch` belongs in listings like `/launches`.
+    pub fn shows(&self, launch: &Launch) -> bool {
+        !(self.exclude_suborbital && launch.suborbital)
+    }
+
+    /// Whether subscription shouldn't notify about `launch`.
+    pub fn filters_out(&self, launch: &Launch) -> bool {
+        !self.shows(launch)
+            || launch
+                .tags
+                .iter()
+                .any(|tag| self.muted_tags.contains(&tag.text.to_lowercase()))
+    }
+
+    /// Whether notification sent `until_launch` seconds before T-0 is due to
+    /// a lead time marked silent, the closest one it has passed.
+    pub fn silent_at(&self, until_launch: i64) -> bool {
+        self.notify_times
+            .iter()
+            .filter(|&&t| t >= until_launch)
+            .min()
+            .is_some_and(|t| self.silent_times.contains(t))
+    }
+
+    /// Quiet hours `now` falls into, in the chat's timezone.
+    pub fn quiet_at(&self, now: DateTime<Utc>) -> Option<QuietHours> {
+        let time = now.with_timezone(&self.timezone).time();
+        self.quiet_hours.filter(|quiet| quiet.contains(time))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatType {
+    #[default]
+    Unknown,
+    Private,
+    Group,
+    Supergroup,
+    Channel,
+}
+
+impl ChatType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChatType::Unknown => "unknown",
+            ChatType::Private => "private",
+            ChatType::Group => "group",
+            ChatType::Supergroup => "supergroup",
+            ChatType::Channel => "channel",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "private" => ChatType::Private,
+            "group" => ChatType::Group,
+            "supergroup" => ChatType::Supergroup,
+            "channel" => ChatType::Channel,
+            _ => ChatType::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for ChatType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What we know about a subscribed chat or topic, for admins.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubscriberInfo {
+    pub chat_type: ChatType,
+    /// Group or channel title, or user's full name.
+    pub title: Option<String>,
+    pub username: Option<String>,
+    pub subscribed_at: Option<DateTime<Utc>>,
+    pub last_delivery: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_digest: Option<DateTime<Utc>>,
+    /// Pinned message about the next launch, and what it shows.
+    pub pinned_message: Option<i32>,
+    pub pinned_launch: Option<u64>,
+    pub pinned_text: Option<String>,
+}
+
+/// Things worth counting for admin statistics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Event {
+    Subscribed {
+        chat_id: i64,
+    },
+    Unsubscribed {
+        chat_id: i64,
+    },
+    /// Chat was unsubscribed because of a telegram api error, named by `reason`.
+    AutoUnsubscribed {
+        chat_id: i64,
+        reason: String,
+    },
+    NotificationSent {
         chat_id: i64,
         launch_id: u64,
-        t0: DateTime<Utc>,
-    ) -> sled::Result<()> {
+    },
+    NotificationFailed {
+        chat_id: i64,
+        launch_id: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdminRole {
+    /// Configured in config file, can manage other admins.
+    Owner,
+    Admin,
+}
+
+impl AdminRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AdminRole::Owner => "owner",
+            AdminRole::Admin => "admin",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "owner" => Some(AdminRole::Owner),
+            "admin" => Some(AdminRole::Admin),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    /// Admin chat the action was performed in.
+    pub chat_id: i64,
+    pub user_id: Option<u64>,
+    pub username: Option<String>,
+    pub action: String,
+}
+
+/// Full database contents, used for backups and moving between backends.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Dump {
+    pub subscribers: Vec<Subscriber>,
+    pub settings: HashMap<Subscriber, ChatSettings>,
+    #[serde(default)]
+    pub subscriber_info: HashMap<Subscriber, SubscriberInfo>,
+    pub notifications: HashMap<Subscriber, HashMap<u64, i64>>,
+    #[serde(default)]
+    pub follows: HashMap<Subscriber, Follows>,
+    pub launches: Vec<Launch>,
+    #[serde(default)]
+    pub events: Vec<(DateTime<Utc>, Event)>,
+    #[serde(default)]
+    pub meta: HashMap<String, String>,
+    #[serde(default)]
+    pub admins: HashMap<i64, AdminRole>,
+    #[serde(default)]
+    pub audit: Vec<AuditEntry>,
+}
+
+pub fn open<P>(backend: StorageBackend, path: P) -> DbResult<Db>
+where
+    P: AsRef<Path>,
+{
+    let db: Db = match backend {
+        StorageBackend::Sled => Arc::new(SledStorage::open(path)?),
+        StorageBackend::Sqlite => Arc::new(SqliteStorage::open(path)?),
+    };
+    Ok(db)
+}
+
+/// Takes an exclusive lock on `<path>.lock`, creating missing parent
+/// directories, so that two bot instances can't use the same database.
+fn lock(path: &Path) -> DbResult<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let file = File::create(lock_path)?;
+    file.try_lock_exclusive()
+        .map_err(|_| DbError::Locked(path.to_path_buf()))?;
+    Ok(file)
+}
+
+pub trait Storage: Send + Sync {
+    fn subscribers(&self) -> DbResult<Vec<Subscriber>>;
+
+    fn subscribers_count(&self) -> DbResult<usize> {
+        Ok(self.subscribers()?.len())
+    }
+
+    /// The chat itself and every forum topic of it that are subscribed.
+    fn chat_subscribers(&self, chat_id: i64) -> DbResult<Vec<Subscriber>> {
+        Ok(self
+            .subscribers()?
+            .into_iter()
+            .filter(|sub| sub.chat_id == chat_id)
+            .collect())
+    }
+
+    fn is_subscribed(&self, sub: Subscriber) -> DbResult<bool>;
+
+    fn subscribe(&self, sub: Subscriber) -> DbResult<()>;
+
+    fn unsubscribe(&self, sub: Subscriber) -> DbResult<()>;
+
+    /// Moves subscriptions, notifications and settings of `old_chat_id` and its
+    /// topics over to `new_chat_id`. Returns `false` if nothing was subscribed.
+    fn replace_chat_id(&self, old_chat_id: i64, new_chat_id: i64) -> DbResult<bool>;
+
+    /// Default info if there's none stored for `sub`.
+    fn get_subscriber_info(&self, sub: Subscriber) -> DbResult<SubscriberInfo>;
+
+    /// Doesn't do anything if `sub` isn't subscribed.
+    fn set_subscriber_info(&self, sub: Subscriber, info: &SubscriberInfo) -> DbResult<()>;
+
+    fn record_delivery(&self, sub: Subscriber) -> DbResult<()> {
+        if !self.is_subscribed(sub)? {
+            return Ok(());
+        }
+        let mut info = self.get_subscriber_info(sub)?;
+        info.last_delivery = Some(Utc::now());
+        self.set_subscriber_info(sub, &info)
+    }
+
+    fn record_error(&self, sub: Subscriber, error: &str) -> DbResult<()> {
+        if !self.is_subscribed(sub)? {
+            return Ok(());
+        }
+        let mut info = self.get_subscriber_info(sub)?;
+        info.last_error = Some(error.to_string());
+        info.last_error_at = Some(Utc::now());
+        self.set_subscriber_info(sub, &info)
+    }
+
+    /// Launch id to seconds left until T-0 at the moment of the last notification.
+    fn get_notified(&self, sub: Subscriber) -> DbResult<HashMap<u64, i64>>;
+
+    fn add_notified(&self, sub: Subscriber, notified: &HashMap<u64, i64>) -> DbResult<()>;
+
+    fn get_settings(&self, sub: Subscriber) -> DbResult<ChatSettings>;
+
+    fn set_settings(&self, sub: Subscriber, settings: &ChatSettings) -> DbResult<()>;
+
+    /// Settings of every chat and topic that has any stored, subscribed or not.
+    fn all_settings(&self) -> DbResult<Vec<(Subscriber, ChatSettings)>>;
+
+    /// Launches `sub` follows regardless of subscription.
+    fn get_follows(&self, sub: Subscriber) -> DbResult<Follows>;
+
+    /// Starts following `launch_id`, or replaces the follow of followed one.
+    fn set_follow(&self, sub: Subscriber, launch_id: u64, follow: Follow) -> DbResult<()>;
+
+    /// Returns `false` if `launch_id` wasn't followed.
+    fn unfollow(&self, sub: Subscriber, launch_id: u64) -> DbResult<bool>;
+
+    /// Follows of every chat and topic following anything.
+    fn all_follows(&self) -> DbResult<Vec<(Subscriber, Follows)>>;
+
+    /// Chats and topics following `launch_id`, with their last notification.
+    fn followers(&self, launch_id: u64) -> DbResult<Vec<(Subscriber, Option<i64>)>> {
+        Ok(self
+            .all_follows()?
+            .into_iter()
+            .filter_map(|(sub, follows)| Some((sub, follows.get(&launch_id)?.time_diff)))
+            .collect())
+    }
+
+    /// Follows `launches` of vehicles and tags chats follow, unless muted or
+    /// followed already.
+    fn follow_matching_launches(&self, launches: &[Launch]) -> DbResult<()> {
+        for (sub, settings) in self.all_settings()? {
+            if settings.vehicles.is_empty() && settings.tags.is_empty() {
+                continue;
+            }
+            let follows = self.get_follows(sub)?;
+            for launch in launches {
+                if settings.follows_launch(launch)
+                    && !settings.muted_launches.contains(&launch.id)
+                    && !follows.contains_key(&launch.id)
+                {
+                    info!("{} follows launch {} by vehicle or tag", sub, launch.id);
+                    let follow = Follow {
+                        time_diff: None,
+                        matched: true,
+                    };
+                    self.set_follow(sub, launch.id, follow)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops follows of `sub` made for vehicles and tags it doesn't follow
+    /// anymore, keeping ones made with `/follow`.
+    fn unfollow_unmatched(&self, sub: Subscriber, launches: &[Launch]) -> DbResult<()> {
+        let settings = self.get_settings(sub)?;
+        let follows = self.get_follows(sub)?;
+        for launch in launches {
+            if follows.get(&launch.id).is_some_and(|follow| follow.matched)
+                && !settings.follows_launch(launch)
+            {
+                self.unfollow(sub, launch.id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops every follow of `sub`, along with vehicles and tags it follows,
+    /// so launches don't get followed again for a chat that's gone.
+    fn unfollow_all(&self, sub: Subscriber) -> DbResult<()> {
+        for launch_id in self.get_follows(sub)?.into_keys() {
+            self.unfollow(sub, launch_id)?;
+        }
+        let mut settings = self.get_settings(sub)?;
+        if !settings.vehicles.is_empty() || !settings.tags.is_empty() {
+            settings.vehicles.clear();
+            settings.tags.clear();
+            self.set_settings(sub, &settings)?;
+        }
+        Ok(())
+    }
+
+    /// [`Storage::unfollow_all`] for `chat_id` and its topics.
+    fn unfollow_chat(&self, chat_id: i64) -> DbResult<()> {
+        let follows = self.all_follows()?.into_iter().map(|(sub, _)| sub);
+        let settings = self.all_settings()?.into_iter().map(|(sub, _)| sub);
+        let mut subs = follows
+            .chain(settings)
+            .filter(|sub| sub.chat_id == chat_id)
+            .collect::<Vec<_>>();
+        subs.sort_unstable_by_key(|sub| sub.thread_id);
+        subs.dedup();
+        for sub in subs {
+            self.unfollow_all(sub)?;
+        }
+        Ok(())
+    }
+
+    fn set_launches(&self, launches: &[Launch]) -> DbResult<()>;
+
+    fn get_launches(&self) -> DbResult<Vec<Launch>>;
+
+    fn add_event(&self, at: DateTime<Utc>, event: &Event) -> DbResult<()>;
+
+    fn record_event(&self, event: &Event) -> DbResult<()> {
+        self.add_event(Utc::now(), event)
+    }
+
+    /// Events starting from `since`, oldest first.
+    fn events_since(&self, since: DateTime<Utc>) -> DbResult<Vec<(DateTime<Utc>, Event)>>;
+
+    /// Removes events older than `before`, returning number of removed ones.
+    fn prune_events(&self, before: DateTime<Utc>) -> DbResult<usize>;
+
+    /// Bot-wide values not tied to any chat.
+    fn get_meta(&self, key: &str) -> DbResult<Option<String>>;
+
+    fn set_meta(&self, key: &str, value: &str) -> DbResult<()>;
+
+    fn all_meta(&self) -> DbResult<HashMap<String, String>>;
+
+    fn last_fetch(&self) -> DbResult<Option<DateTime<Utc>>> {
+        let last_fetch = self
+            .get_meta(LAST_FETCH_KEY)?
+            .and_then(|val| DateTime::parse_from_rfc3339(&val).ok())
+            .map(|t| t.with_timezone(&Utc));
+        Ok(last_fetch)
+    }
+
+    fn set_last_fetch(&self, at: DateTime<Utc>) -> DbResult<()> {
+        self.set_meta(LAST_FETCH_KEY, &at.to_rfc3339())
+    }
+
+    /// Source of the notification template set by admins, `None` for the
+    /// built in format.
+    fn notification_template(&self) -> DbResult<Option<String>> {
+        let template = self
+            .get_meta(NOTIFICATION_TEMPLATE_KEY)?
+            .filter(|template| !template.is_empty());
+        Ok(template)
+    }
+
+    fn set_notification_template(&self, template: Option<&str>) -> DbResult<()> {
+        self.set_meta(NOTIFICATION_TEMPLATE_KEY, template.unwrap_or_default())
+    }
+
+    /// Remembers broadcast of `preview_id` message awaiting confirmation in
+    /// admin chat, replacing one not confirmed yet.
+    fn set_pending_broadcast(&self, chat_id: i64, preview_id: i32) -> DbResult<()> {
+        self.set_meta(
+            &format!("{}{}", PENDING_BROADCAST_KEY_PREFIX, chat_id),
+            &preview_id.to_string(),
+        )
+    }
+
+    /// Whether broadcast of `preview_id` was awaiting confirmation, so it's
+    /// confirmed or cancelled once however many times buttons get pressed.
+    fn take_pending_broadcast(&self, chat_id: i64, preview_id: i32) -> DbResult<bool> {
+        let key = format!("{}{}", PENDING_BROADCAST_KEY_PREFIX, chat_id);
+        if self.get_meta(&key)? != Some(preview_id.to_string()) {
+            return Ok(false);
+        }
+        self.set_meta(&key, "")?;
+        Ok(true)
+    }
+
+    fn admins(&self) -> DbResult<Vec<(i64, AdminRole)>>;
+
+    fn admin_role(&self, chat_id: i64) -> DbResult<Option<AdminRole>>;
+
+    fn set_admin(&self, chat_id: i64, role: AdminRole) -> DbResult<()>;
+
+    /// Returns `false` if `chat_id` wasn't an admin.
+    fn remove_admin(&self, chat_id: i64) -> DbResult<bool>;
+
+    fn is_admin(&self, chat_id: i64) -> DbResult<bool> {
+        Ok(self.admin_role(chat_id)?.is_some())
+    }
+
+    /// Makes configured chats owners, revoking access of owners removed from
+    /// config. Changes are audited as done in the chat itself.
+    fn seed_admins(&self, owners: &[i64]) -> DbResult<()> {
+        let audit = |chat_id: i64, action: &str| {
+            self.add_audit(&AuditEntry {
+                at: Utc::now(),
+                chat_id,
+                user_id: None,
+                username: None,
+                action: action.to_string(),
+            })
+        };
+        for (chat_id, role) in self.admins()? {
+            if role == AdminRole::Owner && !owners.contains(&chat_id) {
+                info!("chat {} is no longer configured as owner", chat_id);
+                self.remove_admin(chat_id)?;
+                audit(chat_id, "config: removed owner")?;
+            }
+        }
+        for &chat_id in owners {
+            if self.admin_role(chat_id)? != Some(AdminRole::Owner) {
+                self.set_admin(chat_id, AdminRole::Owner)?;
+                audit(chat_id, "config: made owner")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_audit(&self, entry: &AuditEntry) -> DbResult<()>;
+
+    /// Up to `limit` latest audit entries, newest first.
+    fn audit_log(&self, limit: usize) -> DbResult<Vec<AuditEntry>>;
+
+    /// Checks every stored record, returning descriptions of the corrupt ones.
+    fn validate(&self) -> DbResult<Vec<String>>;
+
+    fn export(&self) -> DbResult<Dump> {
+        let subscribers = self.subscribers()?;
+        let mut subscriber_info = HashMap::new();
+        let mut notifications = HashMap::new();
+        for &sub in &subscribers {
+            subscriber_info.insert(sub, self.get_subscriber_info(sub)?);
+            notifications.insert(sub, self.get_notified(sub)?);
+        }
+        Ok(Dump {
+            subscribers,
+            settings: self.all_settings()?.into_iter().collect(),
+            subscriber_info,
+            notifications,
+            follows: self.all_follows()?.into_iter().collect(),
+            launches: self.get_launches()?,
+            events: self.events_since(DateTime::<Utc>::MIN_UTC)?,
+            meta: self.all_meta()?,
+            admins: self.admins()?.into_iter().collect(),
+            audit: self.audit_log(usize::MAX)?.into_iter().rev().collect(),
+        })
+    }
+
+    fn import(&self, dump: &Dump) -> DbResult<()> {
+        for &sub in &dump.subscribers {
+            self.subscribe(sub)?;
+        }
+        for (&sub, info) in &dump.subscriber_info {
+            self.set_subscriber_info(sub, info)?;
+        }
+        for (&sub, settings) in &dump.settings {
+            self.set_settings(sub, settings)?;
+        }
+        for (&sub, notified) in &dump.notifications {
+            self.add_notified(sub, notified)?;
+        }
+        for (&sub, follows) in &dump.follows {
+            for (&launch_id, &follow) in follows {
+                self.set_follow(sub, launch_id, follow)?;
+            }
+        }
+        self.set_launches(&dump.launches)?;
+        for (at, event) in &dump.events {
+            self.add_event(*at, event)?;
+        }
+        for (key, value) in &dump.meta {
+            self.set_meta(key, value)?;
+        }
+        for (&chat_id, &role) in &dump.admins {
+            self.set_admin(chat_id, role)?;
+        }
+        for entry in &dump.audit {
+            self.add_audit(entry)?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn set_notified(&self, sub: Subscriber, launch_id: u64, t0: DateTime<Utc>) -> DbResult<()> {
         let time_diff = t0.timestamp() - Utc::now().timestamp();
-        self.db.set_merge_operator(merge_add);
-        self.db.merge(
-            chat_id.to_string(),
-            serde_json::to_vec(&HashMap::from([(launch_id, time_diff)])).unwrap_or_default(),
-        )?;
-        info!("set notified for {} {}", chat_id, launch_id);
+        if self.is_subscribed(sub)? {
+            self.add_notified(sub, &HashMap::from([(launch_id, time_diff)]))?;
+            info!("set notified for {} {}", sub, launch_id);
+        }
+        if let Some(&follow) = self.get_follows(sub)?.get(&launch_id) {
+            let follow = Follow {
+                time_diff: Some(time_diff),
+                ..follow
+            };
+            self.set_follow(sub, launch_id, follow)?;
+            info!("set notified for follower {} {}", sub, launch_id);
+        }
         Ok(())
     }
 
     #[tracing::instrument(skip_all)]
-    pub fn get_unnotified(
-        &self,
-        launch_id: u64,
-        launch_t0: DateTime<Utc>,
-    ) -> sled::Result<Vec<i64>> {
-        let ids = self
-            .db
-            .iter()
-            .filter_map(|a| {
-                let Ok((key, val)) = a else { return None };
-                let launches: HashMap<u64, i64> = serde_json::from_slice(&val).ok()?;
-                let chat_id = serde_json::from_slice::<i64>(&key).ok()?;
-                let time_diff = launches.get(&launch_id).unwrap_or(&(24 * 3600));
-                let until_launch = launch_t0.timestamp() - Utc::now().timestamp();
-                for t in NOTIFY_TIMES {
-                    if *time_diff <= t {
+    fn get_unnotified(&self, launch: &Launch) -> DbResult<Vec<Subscriber>> {
+        let (launch_id, Some(launch_t0)) = (launch.id, launch.t0) else {
+            return Ok(vec![]);
+        };
+        let until_launch = launch_t0.timestamp() - Utc::now().timestamp();
+        let followers: HashMap<Subscriber, Option<i64>> =
+            self.followers(launch_id)?.into_iter().collect();
+        let mut subs = self.subscribers()?;
+        for sub in followers.keys() {
+            if !subs.contains(sub) {
+                subs.push(*sub);
+            }
+        }
+        let mut ids = vec![];
+        for sub in subs {
+            let settings = match self.get_settings(sub) {
+                Ok(settings) => settings,
+                Err(err) => {
+                    warn!("skipping subscriber {} with bad data: {}", sub, err);
+                    continue;
+                }
+            };
+            if settings.muted_launches.contains(&launch_id) {
+                continue;
+            }
+            // follows override filters
+            let time_diff = match followers.get(&sub) {
+                Some(&time_diff) => time_diff,
+                None if settings.digest.is_some() || settings.filters_out(launch) => continue,
+                None => match self.get_notified(sub) {
+                    Ok(launches) => launches.get(&launch_id).copied(),
+                    Err(err) => {
+                        warn!("skipping subscriber {} with bad data: {}", sub, err);
                         continue;
                     }
-                    if until_launch <= t {
-                        debug!(
-                            "launch_id={} launch_t0={} t={} time_diff={} until_launch={}",
-                            launch_id, launch_t0, t, time_diff, until_launch
-                        );
-                        return Some(chat_id);
-                    }
+                },
+            };
+            let time_diff = time_diff.unwrap_or(24 * 3600);
+            for &t in &settings.notify_times {
+                if time_diff <= t {
+                    continue;
                 }
-                None
-            })
-            .collect();
+                if until_launch <= t {
+                    debug!(
+                        "launch_id={} launch_t0={} t={} time_diff={} until_launch={}",
+                        launch_id, launch_t0, t, time_diff, until_launch
+                    );
+                    ids.push(sub);
+                    break;
+                }
+            }
+        }
         Ok(ids)
     }
+}
 
-    pub fn subscribe(&self, chat_id: i64) -> sled::Result<()> {
-        self.db.set_merge_operator(merge_add);
-        self.db.merge(
-            chat_id.to_string(),
-            serde_json::to_vec::<HashMap<u64, i64>>(&HashMap::new()).unwrap(),
-        )?;
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Both backends on fresh databases, with directories they live in.
+    fn backends() -> Vec<(TempDir, Db)> {
+        [StorageBackend::Sled, StorageBackend::Sqlite]
+            .into_iter()
+            .map(|backend| {
+                let dir = TempDir::new().unwrap();
+                let db = open(backend, dir.path().join("db")).unwrap();
+                (dir, db)
+            })
+            .collect()
     }
 
-    pub fn unsubscribe(&self, chat_id: i64) -> sled::Result<()> {
-        self.db.remove(chat_id.to_string())?;
-        Ok(())
+    fn topic(chat_id: i64, thread_id: i32) -> Subscriber {
+        Subscriber {
+            chat_id,
+            thread_id: Some(thread_id),
+        }
     }
 
-    pub fn iter(&self) -> sled::Iter {
-        self.db.iter()
+    #[test]
+    fn subscribe() {
+        for (_dir, db) in backends() {
+            db.subscribe(Subscriber::chat(2)).unwrap();
+            db.subscribe(topic(1, 5)).unwrap();
+            db.subscribe(Subscriber::chat(1)).unwrap();
+            db.subscribe(Subscriber::chat(1)).unwrap();
+            assert_eq!(
+                db.subscribers().unwrap(),
+                [Subscriber::chat(1), topic(1, 5), Subscriber::chat(2)]
+            );
+            assert_eq!(
+                db.chat_subscribers(1).unwrap(),
+                [Subscriber::chat(1), topic(1, 5)]
+            );
+            assert!(db.is_subscribed(topic(1, 5)).unwrap());
+            assert!(!db.is_subscribed(topic(1, 6)).unwrap());
+
+            db.unsubscribe(Subscriber::chat(1)).unwrap();
+            assert!(!db.is_subscribed(Subscriber::chat(1)).unwrap());
+            assert_eq!(db.subscribers_count().unwrap(), 2);
+        }
     }
 
-    pub fn replace_chat_id(&self, old_chat_id: i64, new_chat_id: i64) -> sled::Result<bool> {
-        if let Some(data) = self.db.remove(old_chat_id.to_string())? {
-            let new_key = new_chat_id.to_string();
-            if self.db.contains_key(&new_key)? {
-                self.db.set_merge_operator(merge_add);
-                self.db.merge(&new_key, data)?;
-            } else {
-                self.db.insert(&new_key, data)?;
+    #[test]
+    fn notified() {
+        for (_dir, db) in backends() {
+            let sub = Subscriber::chat(1);
+            db.subscribe(sub).unwrap();
+            db.add_notified(sub, &HashMap::from([(10, 3600), (11, 86400)]))
+                .unwrap();
+            db.add_notified(sub, &HashMap::from([(10, 900)])).unwrap();
+            assert_eq!(
+                db.get_notified(sub).unwrap(),
+                HashMap::from([(10, 900), (11, 86400)])
+            );
+            assert!(db.get_notified(topic(1, 5)).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn follows() {
+        for (_dir, db) in backends() {
+            let sub = Subscriber::chat(1);
+            let matched = Follow {
+                time_diff: None,
+                matched: true,
+            };
+            db.set_follow(sub, 10, Follow::default()).unwrap();
+            db.set_follow(sub, 11, matched).unwrap();
+            db.set_follow(topic(2, 5), 10, Follow::default()).unwrap();
+            assert_eq!(
+                db.get_follows(sub).unwrap(),
+                HashMap::from([(10, Follow::default()), (11, matched)])
+            );
+            let mut followers = db.followers(10).unwrap();
+            followers.sort();
+            assert_eq!(followers, [(sub, None), (topic(2, 5), None)]);
+            assert_eq!(db.all_follows().unwrap().len(), 2);
+
+            let notified = Follow {
+                time_diff: Some(3600),
+                matched: true,
+            };
+            db.set_follow(sub, 11, notified).unwrap();
+            assert_eq!(db.get_follows(sub).unwrap()[&11], notified);
+
+            assert!(db.unfollow(sub, 10).unwrap());
+            assert!(!db.unfollow(sub, 10).unwrap());
+            assert_eq!(db.followers(10).unwrap(), [(topic(2, 5), None)]);
+        }
+    }
+
+    #[test]
+    fn replace_chat_id() {
+        for (_dir, db) in backends() {
+            let settings = ChatSettings {
+                language: Some(Lang::Ru),
+                ..Default::default()
+            };
+            for sub in [Subscriber::chat(1), topic(1, 5)] {
+                db.subscribe(sub).unwrap();
+                db.set_settings(sub, &settings).unwrap();
+                db.add_notified(sub, &HashMap::from([(10, 3600)])).unwrap();
+                db.set_follow(sub, 11, Follow::default()).unwrap();
             }
-            Ok(true)
-        } else {
-            Ok(false)
+            assert!(db.replace_chat_id(1, 2).unwrap());
+            assert!(!db.replace_chat_id(1, 2).unwrap());
+
+            assert!(db.chat_subscribers(1).unwrap().is_empty());
+            assert!(db
+                .all_settings()
+                .unwrap()
+                .iter()
+                .all(|(s, _)| s.chat_id == 2));
+            for sub in [Subscriber::chat(2), topic(2, 5)] {
+                assert!(db.is_subscribed(sub).unwrap());
+                assert_eq!(db.get_settings(sub).unwrap(), settings);
+                assert_eq!(db.get_notified(sub).unwrap(), HashMap::from([(10, 3600)]));
+                assert!(db.get_follows(sub).unwrap().contains_key(&11));
+            }
+            assert!(db.get_follows(Subscriber::chat(1)).unwrap().is_empty());
         }
     }
-}
 
-fn merge_add(_key: &[u8], old_value: Option<&[u8]>, merged_bytes: &[u8]) -> Option<Vec<u8>> {
-    let mut old: HashMap<u64, i64> = old_value
-        .map(|b| serde_json::from_slice(b).expect("bad value in db"))
-        .unwrap_or_default();
-    let new: HashMap<u64, i64> = serde_json::from_slice(merged_bytes).expect("bad value");
-    old.extend(new);
-    Some(serde_json::to_vec(&old).expect("oops"))
+    #[test]
+    fn export_import() {
+        let (_source_dir, source) = backends().remove(0);
+        let sub = topic(1, 5);
+        source.subscribe(sub).unwrap();
+        source
+            .set_subscriber_info(
+                sub,
+                &SubscriberInfo {
+                    chat_type: ChatType::Supergroup,
+                    title: Some("Rockets".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        source.set_settings(sub, &ChatSettings::default()).unwrap();
+        source
+            .add_notified(sub, &HashMap::from([(10, 3600)]))
+            .unwrap();
+        source
+            .set_follow(Subscriber::chat(3), 11, Follow::default())
+            .unwrap();
+        source
+            .add_event(
+                DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+                &Event::Subscribed { chat_id: 1 },
+            )
+            .unwrap();
+        source.set_meta("key", "value").unwrap();
+        source.set_admin(7, AdminRole::Owner).unwrap();
+        source
+            .add_audit(&AuditEntry {
+                at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+                chat_id: 7,
+                user_id: Some(8),
+                username: None,
+                action: "Stats".to_string(),
+            })
+            .unwrap();
+        let dump = source.export().unwrap();
+
+        for (_dir, db) in backends() {
+            db.import(&dump).unwrap();
+            assert_eq!(
+                serde_json::to_value(db.export().unwrap()).unwrap(),
+                serde_json::to_value(&dump).unwrap()
+            );
+        }
+    }
+
+    fn time(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn quiet_hours_contains() {
+        let day = QuietHours {
+            start: time("13:00"),
+            end: time("15:00"),
+            mode: QuietMode::Drop,
+        };
+        assert!(day.contains(time("13:00")));
+        assert!(day.contains(time("14:59")));
+        assert!(!day.contains(time("15:00")));
+        assert!(!day.contains(time("12:59")));
+
+        let night = QuietHours {
+            start: time("23:00"),
+            end: time("07:00"),
+            ..day
+        };
+        for inside in ["23:00", "23:59", "00:00", "06:59"] {
+            assert!(night.contains(time(inside)), "{}", inside);
+        }
+        for outside in ["07:00", "12:00", "22:59"] {
+            assert!(!night.contains(time(outside)), "{}", outside);
+        }
+    }
+
+    #[test]
+    fn digest_daily_last_due() {
+        let digest = Digest {
+            period: DigestPeriod::Daily,
+            time: time("08:00"),
+        };
+        let berlin = chrono_tz::Europe::Berlin;
+        assert_eq!(
+            digest.last_due(berlin, utc("2024-06-10T07:00:00Z")),
+            utc("2024-06-10T06:00:00Z")
+        );
+        assert_eq!(
+            digest.last_due(berlin, utc("2024-06-10T06:00:00Z")),
+            utc("2024-06-10T06:00:00Z")
+        );
+        assert_eq!(
+            digest.last_due(berlin, utc("2024-06-10T05:59:00Z")),
+            utc("2024-06-09T06:00:00Z")
+        );
+    }
+
+    #[test]
+    fn digest_weekly_last_due() {
+        // 2024-06-10 is a Monday
+        let digest = Digest {
+            period: DigestPeriod::Weekly(Weekday::Mon),
+            time: time("08:00"),
+        };
+        let berlin = chrono_tz::Europe::Berlin;
+        assert_eq!(
+            digest.last_due(berlin, utc("2024-06-12T12:00:00Z")),
+            utc("2024-06-10T06:00:00Z")
+        );
+        assert_eq!(
+            digest.last_due(berlin, utc("2024-06-10T06:00:00Z")),
+            utc("2024-06-10T06:00:00Z")
+        );
+        // earlier on the day rolls back a week
+        assert_eq!(
+            digest.last_due(berlin, utc("2024-06-10T05:00:00Z")),
+            utc("2024-06-03T06:00:00Z")
+        );
+        // Monday in UTC, still Sunday in Los Angeles
+        assert_eq!(
+            digest.last_due(chrono_tz::America::Los_Angeles, utc("2024-06-10T05:00:00Z")),
+            utc("2024-06-03T15:00:00Z")
+        );
+    }
+
+    #[test]
+    fn digest_daylight_saving() {
+        let digest = Digest {
+            period: DigestPeriod::Daily,
+            time: time("02:30"),
+        };
+        let berlin = chrono_tz::Europe::Berlin;
+        // 02:30 is skipped on 2024-03-31, it's 03:30 CEST instead
+        assert_eq!(
+            digest.last_due(berlin, utc("2024-03-31T12:00:00Z")),
+            utc("2024-03-31T01:30:00Z")
+        );
+        // and happens twice on 2024-10-27, the first one counts
+        assert_eq!(
+            digest.last_due(berlin, utc("2024-10-27T12:00:00Z")),
+            utc("2024-10-27T00:30:00Z")
+        );
+    }
 }
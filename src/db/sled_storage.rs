@@ -0,0 +1,422 @@
+use std::{collections::HashMap, fs::File, path::Path, sync::Arc};
+
+use chrono::{DateTime, Utc};
+
+use super::{
+    AdminRole, AuditEntry, ChatSettings, DbResult, Event, Follow, Follows, Storage, Subscriber,
+    SubscriberInfo,
+};
+use crate::types::Launch;
+
+const LAUNCHES_KEY: &str = "launches";
+const SETTINGS_TREE: &str = "settings";
+const SUBSCRIBER_INFO_TREE: &str = "subscriber_info";
+const FOLLOWS_TREE: &str = "follows";
+const EVENTS_TREE: &str = "events";
+const META_TREE: &str = "meta";
+const ADMINS_TREE: &str = "admins";
+const AUDIT_TREE: &str = "audit";
+
+/// Subscribers are keys of the default tree, each holding a map of notified
+/// launches; launches list is stored in the same tree under `launches` key.
+/// Subscriber keys are written the way [`Subscriber`] displays, so chats
+/// subscribed before topics support keep their plain chat id keys.
+#[derive(Debug, Clone)]
+pub struct SledStorage {
+    db: sled::Db,
+    settings: sled::Tree,
+    subscriber_info: sled::Tree,
+    /// Keyed by subscriber, holding a map of followed launches.
+    follows: sled::Tree,
+    /// Keyed by big endian millisecond timestamp followed by unique id.
+    events: sled::Tree,
+    meta: sled::Tree,
+    admins: sled::Tree,
+    /// Keyed the same way as `events`.
+    audit: sled::Tree,
+    _lock: Arc<File>,
+}
+
+impl SledStorage {
+    pub fn open<P>(path: P) -> DbResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let lock = super::lock(path.as_ref())?;
+        let db = sled::open(path)?;
+        db.set_merge_operator(merge_add);
+        let settings = db.open_tree(SETTINGS_TREE)?;
+        let subscriber_info = db.open_tree(SUBSCRIBER_INFO_TREE)?;
+        let follows = db.open_tree(FOLLOWS_TREE)?;
+        let events = db.open_tree(EVENTS_TREE)?;
+        let meta = db.open_tree(META_TREE)?;
+        let admins = db.open_tree(ADMINS_TREE)?;
+        let audit = db.open_tree(AUDIT_TREE)?;
+        Ok(Self {
+            db,
+            settings,
+            subscriber_info,
+            follows,
+            events,
+            meta,
+            admins,
+            audit,
+            _lock: Arc::new(lock),
+        })
+    }
+}
+
+impl Storage for SledStorage {
+    fn subscribers(&self) -> DbResult<Vec<Subscriber>> {
+        let mut ids = vec![];
+        for item in self.db.iter() {
+            let (key, _) = item?;
+            if let Ok(sub) = String::from_utf8_lossy(&key).parse() {
+                ids.push(sub);
+            }
+        }
+        Ok(ids)
+    }
+
+    fn is_subscribed(&self, sub: Subscriber) -> DbResult<bool> {
+        Ok(self.db.contains_key(sub.to_string())?)
+    }
+
+    fn subscribe(&self, sub: Subscriber) -> DbResult<()> {
+        self.add_notified(sub, &HashMap::new())
+    }
+
+    fn unsubscribe(&self, sub: Subscriber) -> DbResult<()> {
+        self.db.remove(sub.to_string())?;
+        self.subscriber_info.remove(sub.to_string())?;
+        Ok(())
+    }
+
+    fn replace_chat_id(&self, old_chat_id: i64, new_chat_id: i64) -> DbResult<bool> {
+        let moved = |old: Subscriber| Subscriber {
+            chat_id: new_chat_id,
+            ..old
+        };
+        for (sub, settings) in self.all_settings()? {
+            if sub.chat_id == old_chat_id {
+                self.settings.remove(sub.to_string())?;
+                self.set_settings(moved(sub), &settings)?;
+            }
+        }
+        for (sub, follows) in self.all_follows()? {
+            if sub.chat_id == old_chat_id {
+                self.follows.remove(sub.to_string())?;
+                self.follows
+                    .insert(moved(sub).to_string(), serde_json::to_vec(&follows)?)?;
+            }
+        }
+        let subscribers = self.chat_subscribers(old_chat_id)?;
+        for &sub in &subscribers {
+            if let Some(info) = self.subscriber_info.remove(sub.to_string())? {
+                self.subscriber_info.insert(moved(sub).to_string(), info)?;
+            }
+            if let Some(data) = self.db.remove(sub.to_string())? {
+                self.db.merge(moved(sub).to_string(), data)?;
+            }
+        }
+        Ok(!subscribers.is_empty())
+    }
+
+    fn get_subscriber_info(&self, sub: Subscriber) -> DbResult<SubscriberInfo> {
+        let info = match self.subscriber_info.get(sub.to_string())? {
+            Some(val) => serde_json::from_slice(&val)?,
+            None => SubscriberInfo::default(),
+        };
+        Ok(info)
+    }
+
+    fn set_subscriber_info(&self, sub: Subscriber, info: &SubscriberInfo) -> DbResult<()> {
+        if self.is_subscribed(sub)? {
+            self.subscriber_info
+                .insert(sub.to_string(), serde_json::to_vec(info)?)?;
+        }
+        Ok(())
+    }
+
+    fn get_notified(&self, sub: Subscriber) -> DbResult<HashMap<u64, i64>> {
+        let notified = match self.db.get(sub.to_string())? {
+            Some(val) => serde_json::from_slice(&val)?,
+            None => HashMap::new(),
+        };
+        Ok(notified)
+    }
+
+    fn add_notified(&self, sub: Subscriber, notified: &HashMap<u64, i64>) -> DbResult<()> {
+        self.db
+            .merge(sub.to_string(), serde_json::to_vec(notified)?)?;
+        Ok(())
+    }
+
+    fn get_settings(&self, sub: Subscriber) -> DbResult<ChatSettings> {
+        let settings = match self.settings.get(sub.to_string())? {
+            Some(val) => serde_json::from_slice(&val)?,
+            None => ChatSettings::default(),
+        };
+        Ok(settings)
+    }
+
+    fn set_settings(&self, sub: Subscriber, settings: &ChatSettings) -> DbResult<()> {
+        self.settings
+            .insert(sub.to_string(), serde_json::to_vec(settings)?)?;
+        Ok(())
+    }
+
+    fn all_settings(&self) -> DbResult<Vec<(Subscriber, ChatSettings)>> {
+        let mut all = vec![];
+        for item in self.settings.iter() {
+            let (key, val) = item?;
+            let Ok(sub) = String::from_utf8_lossy(&key).parse() else {
+                continue;
+            };
+            all.push((sub, serde_json::from_slice(&val)?));
+        }
+        Ok(all)
+    }
+
+    fn get_follows(&self, sub: Subscriber) -> DbResult<Follows> {
+        let follows = match self.follows.get(sub.to_string())? {
+            Some(val) => serde_json::from_slice(&val)?,
+            None => HashMap::new(),
+        };
+        Ok(follows)
+    }
+
+    fn set_follow(&self, sub: Subscriber, launch_id: u64, follow: Follow) -> DbResult<()> {
+        let mut follows = self.get_follows(sub)?;
+        follows.insert(launch_id, follow);
+        self.follows
+            .insert(sub.to_string(), serde_json::to_vec(&follows)?)?;
+        Ok(())
+    }
+
+    fn unfollow(&self, sub: Subscriber, launch_id: u64) -> DbResult<bool> {
+        let mut follows = self.get_follows(sub)?;
+        if follows.remove(&launch_id).is_none() {
+            return Ok(false);
+        }
+        if follows.is_empty() {
+            self.follows.remove(sub.to_string())?;
+        } else {
+            self.follows
+                .insert(sub.to_string(), serde_json::to_vec(&follows)?)?;
+        }
+        Ok(true)
+    }
+
+    fn all_follows(&self) -> DbResult<Vec<(Subscriber, Follows)>> {
+        let mut all = vec![];
+        for item in self.follows.iter() {
+            let (key, val) = item?;
+            let Ok(sub) = String::from_utf8_lossy(&key).parse() else {
+                continue;
+            };
+            all.push((sub, serde_json::from_slice(&val)?));
+        }
+        Ok(all)
+    }
+
+    fn set_launches(&self, launches: &[Launch]) -> DbResult<()> {
+        self.db
+            .insert(LAUNCHES_KEY, serde_json::to_vec(launches)?)?;
+        Ok(())
+    }
+
+    fn get_launches(&self) -> DbResult<Vec<Launch>> {
+        let launches = self
+            .db
+            .get(LAUNCHES_KEY)?
+            .and_then(|val| serde_json::from_slice::<Vec<Launch>>(&val).ok())
+            .unwrap_or_default();
+        Ok(launches)
+    }
+
+    fn add_event(&self, at: DateTime<Utc>, event: &Event) -> DbResult<()> {
+        let mut key = event_key(at).to_vec();
+        key.extend_from_slice(&self.db.generate_id()?.to_be_bytes());
+        self.events.insert(key, serde_json::to_vec(event)?)?;
+        Ok(())
+    }
+
+    fn events_since(&self, since: DateTime<Utc>) -> DbResult<Vec<(DateTime<Utc>, Event)>> {
+        let mut events = vec![];
+        for item in self.events.range(event_key(since)..) {
+            let (key, val) = item?;
+            let Some(at) = event_time(&key) else {
+                continue;
+            };
+            events.push((at, serde_json::from_slice(&val)?));
+        }
+        Ok(events)
+    }
+
+    fn prune_events(&self, before: DateTime<Utc>) -> DbResult<usize> {
+        let mut removed = 0;
+        for item in self.events.range(..event_key(before)) {
+            let (key, _) = item?;
+            self.events.remove(key)?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    fn get_meta(&self, key: &str) -> DbResult<Option<String>> {
+        let value = self
+            .meta
+            .get(key)?
+            .map(|val| String::from_utf8_lossy(&val).into_owned());
+        Ok(value)
+    }
+
+    fn set_meta(&self, key: &str, value: &str) -> DbResult<()> {
+        self.meta.insert(key, value.as_bytes())?;
+        Ok(())
+    }
+
+    fn all_meta(&self) -> DbResult<HashMap<String, String>> {
+        let mut all = HashMap::new();
+        for item in self.meta.iter() {
+            let (key, val) = item?;
+            all.insert(
+                String::from_utf8_lossy(&key).into_owned(),
+                String::from_utf8_lossy(&val).into_owned(),
+            );
+        }
+        Ok(all)
+    }
+
+    fn admins(&self) -> DbResult<Vec<(i64, AdminRole)>> {
+        let mut admins = vec![];
+        for item in self.admins.iter() {
+            let (key, val) = item?;
+            admins.push((serde_json::from_slice(&key)?, serde_json::from_slice(&val)?));
+        }
+        Ok(admins)
+    }
+
+    fn admin_role(&self, chat_id: i64) -> DbResult<Option<AdminRole>> {
+        let role = match self.admins.get(chat_id.to_string())? {
+            Some(val) => Some(serde_json::from_slice(&val)?),
+            None => None,
+        };
+        Ok(role)
+    }
+
+    fn set_admin(&self, chat_id: i64, role: AdminRole) -> DbResult<()> {
+        self.admins
+            .insert(chat_id.to_string(), serde_json::to_vec(&role)?)?;
+        Ok(())
+    }
+
+    fn remove_admin(&self, chat_id: i64) -> DbResult<bool> {
+        Ok(self.admins.remove(chat_id.to_string())?.is_some())
+    }
+
+    fn add_audit(&self, entry: &AuditEntry) -> DbResult<()> {
+        let mut key = event_key(entry.at).to_vec();
+        key.extend_from_slice(&self.db.generate_id()?.to_be_bytes());
+        self.audit.insert(key, serde_json::to_vec(entry)?)?;
+        Ok(())
+    }
+
+    fn audit_log(&self, limit: usize) -> DbResult<Vec<AuditEntry>> {
+        let mut entries = vec![];
+        for item in self.audit.iter().rev().take(limit) {
+            let (_, val) = item?;
+            entries.push(serde_json::from_slice(&val)?);
+        }
+        Ok(entries)
+    }
+
+    fn validate(&self) -> DbResult<Vec<String>> {
+        let mut problems = vec![];
+        for item in self.db.iter() {
+            let (key, val) = item?;
+            if &*key == LAUNCHES_KEY.as_bytes() {
+                if let Err(err) = serde_json::from_slice::<Vec<Launch>>(&val) {
+                    problems.push(format!("launches: {}", err));
+                }
+                continue;
+            }
+            let key = String::from_utf8_lossy(&key);
+            if let Err(err) = key.parse::<Subscriber>() {
+                problems.push(format!("subscriber key {:?}: {}", key, err));
+            }
+            if let Err(err) = serde_json::from_slice::<HashMap<u64, i64>>(&val) {
+                problems.push(format!("notifications of {}: {}", key, err));
+            }
+        }
+        for item in self.settings.iter() {
+            let (key, val) = item?;
+            let key = String::from_utf8_lossy(&key);
+            if let Err(err) = key.parse::<Subscriber>() {
+                problems.push(format!("settings key {:?}: {}", key, err));
+            }
+            if let Err(err) = serde_json::from_slice::<ChatSettings>(&val) {
+                problems.push(format!("settings of {}: {}", key, err));
+            }
+        }
+        for item in self.follows.iter() {
+            let (key, val) = item?;
+            let key = String::from_utf8_lossy(&key);
+            if let Err(err) = key.parse::<Subscriber>() {
+                problems.push(format!("follows key {:?}: {}", key, err));
+            }
+            if let Err(err) = serde_json::from_slice::<Follows>(&val) {
+                problems.push(format!("follows of {}: {}", key, err));
+            }
+        }
+        for item in self.subscriber_info.iter() {
+            let (key, val) = item?;
+            let key = String::from_utf8_lossy(&key);
+            if let Err(err) = serde_json::from_slice::<SubscriberInfo>(&val) {
+                problems.push(format!("subscriber info of {}: {}", key, err));
+            }
+        }
+        for item in self.events.iter() {
+            let (key, val) = item?;
+            if event_time(&key).is_none() {
+                problems.push(format!("event key {:?}", key));
+            }
+            if let Err(err) = serde_json::from_slice::<Event>(&val) {
+                problems.push(format!("event {:?}: {}", key, err));
+            }
+        }
+        for item in self.admins.iter() {
+            let (key, val) = item?;
+            let key = String::from_utf8_lossy(&key);
+            if let Err(err) = serde_json::from_slice::<AdminRole>(&val) {
+                problems.push(format!("admin role of {}: {}", key, err));
+            }
+        }
+        for item in self.audit.iter() {
+            let (key, val) = item?;
+            if let Err(err) = serde_json::from_slice::<AuditEntry>(&val) {
+                problems.push(format!("audit entry {:?}: {}", key, err));
+            }
+        }
+        Ok(problems)
+    }
+}
+
+fn event_key(at: DateTime<Utc>) -> [u8; 8] {
+    (at.timestamp_millis().max(0) as u64).to_be_bytes()
+}
+
+fn event_time(key: &[u8]) -> Option<DateTime<Utc>> {
+    let millis = u64::from_be_bytes(key.get(..8)?.try_into().ok()?);
+    DateTime::from_timestamp_millis(millis as i64)
+}
+
+fn merge_add(_key: &[u8], old_value: Option<&[u8]>, merged_bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut old: HashMap<u64, i64> = old_value
+        .map(|b| serde_json::from_slice(b).expect("bad value in db"))
+        .unwrap_or_default();
+    let new: HashMap<u64, i64> = serde_json::from_slice(merged_bytes).expect("bad value");
+    old.extend(new);
+    Some(serde_json::to_vec(&old).expect("oops"))
+}
@@ -0,0 +1,758 @@
+use std::{collections::HashMap, fs::File, path::Path, sync::Mutex};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::info;
+
+use super::{
+    AdminRole, AuditEntry, ChatSettings, ChatType, DbResult, Event, Follow, Follows, Storage,
+    Subscriber, SubscriberInfo,
+};
+use crate::types::Launch;
+
+/// Schema migrations, applied in order; `user_version` pragma keeps track of
+/// the ones already applied.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE subscribers (
+        chat_id INTEGER PRIMARY KEY
+    );
+    CREATE TABLE notifications (
+        chat_id INTEGER NOT NULL,
+        launch_id INTEGER NOT NULL,
+        time_diff INTEGER NOT NULL,
+        PRIMARY KEY (chat_id, launch_id)
+    );
+    CREATE TABLE settings (
+        chat_id INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE launches (
+        id INTEGER PRIMARY KEY,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+"#,
+    r#"
+    ALTER TABLE subscribers ADD COLUMN chat_type TEXT NOT NULL DEFAULT 'unknown';
+    ALTER TABLE subscribers ADD COLUMN title TEXT;
+    ALTER TABLE subscribers ADD COLUMN username TEXT;
+    ALTER TABLE subscribers ADD COLUMN subscribed_at INTEGER;
+    ALTER TABLE subscribers ADD COLUMN last_delivery INTEGER;
+    ALTER TABLE subscribers ADD COLUMN last_error TEXT;
+    ALTER TABLE subscribers ADD COLUMN last_error_at INTEGER;
+"#,
+    r#"
+    CREATE TABLE events (
+        id INTEGER PRIMARY KEY,
+        at INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX events_at ON events (at);
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+"#,
+    r#"
+    CREATE TABLE admins (
+        chat_id INTEGER PRIMARY KEY,
+        role TEXT NOT NULL
+    );
+    CREATE TABLE audit (
+        id INTEGER PRIMARY KEY,
+        at INTEGER NOT NULL,
+        chat_id INTEGER NOT NULL,
+        user_id INTEGER,
+        username TEXT,
+        action TEXT NOT NULL
+    );
+"#,
+    r#"
+    CREATE TABLE subscribers_new (
+        chat_id INTEGER NOT NULL,
+        thread_id INTEGER NOT NULL DEFAULT 0,
+        chat_type TEXT NOT NULL DEFAULT 'unknown',
+        title TEXT,
+        username TEXT,
+        subscribed_at INTEGER,
+        last_delivery INTEGER,
+        last_error TEXT,
+        last_error_at INTEGER,
+        PRIMARY KEY (chat_id, thread_id)
+    );
+    INSERT INTO subscribers_new
+        SELECT chat_id, 0, chat_type, title, username, subscribed_at, last_delivery,
+               last_error, last_error_at
+        FROM subscribers;
+    DROP TABLE subscribers;
+    ALTER TABLE subscribers_new RENAME TO subscribers;
+
+    CREATE TABLE notifications_new (
+        chat_id INTEGER NOT NULL,
+        thread_id INTEGER NOT NULL DEFAULT 0,
+        launch_id INTEGER NOT NULL,
+        time_diff INTEGER NOT NULL,
+        PRIMARY KEY (chat_id, thread_id, launch_id)
+    );
+    INSERT INTO notifications_new
+        SELECT chat_id, 0, launch_id, time_diff FROM notifications;
+    DROP TABLE notifications;
+    ALTER TABLE notifications_new RENAME TO notifications;
+
+    CREATE TABLE settings_new (
+        chat_id INTEGER NOT NULL,
+        thread_id INTEGER NOT NULL DEFAULT 0,
+        data TEXT NOT NULL,
+        PRIMARY KEY (chat_id, thread_id)
+    );
+    INSERT INTO settings_new SELECT chat_id, 0, data FROM settings;
+    DROP TABLE settings;
+    ALTER TABLE settings_new RENAME TO settings;
+"#,
+    r#"
+    CREATE TABLE follows (
+        chat_id INTEGER NOT NULL,
+        thread_id INTEGER NOT NULL DEFAULT 0,
+        launch_id INTEGER NOT NULL,
+        time_diff INTEGER,
+        PRIMARY KEY (chat_id, thread_id, launch_id)
+    );
+    CREATE INDEX follows_launch_id ON follows (launch_id);
+"#,
+    r#"
+    ALTER TABLE subscribers ADD COLUMN last_digest INTEGER;
+"#,
+    r#"
+    ALTER TABLE subscribers ADD COLUMN pinned_message INTEGER;
+    ALTER TABLE subscribers ADD COLUMN pinned_launch INTEGER;
+    ALTER TABLE subscribers ADD COLUMN pinned_text TEXT;
+"#,
+    r#"
+    ALTER TABLE follows ADD COLUMN matched INTEGER NOT NULL DEFAULT 0;
+"#,
+];
+
+#[derive(Debug)]
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+    _lock: File,
+}
+
+impl SqliteStorage {
+    pub fn open<P>(path: P) -> DbResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let lock = super::lock(path.as_ref())?;
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            _lock: lock,
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().expect("sqlite connection mutex poisoned")
+    }
+}
+
+/// `thread_id` column value, 0 stands for the chat itself.
+fn thread_id(sub: Subscriber) -> i32 {
+    sub.thread_id.unwrap_or(0)
+}
+
+fn subscriber(chat_id: i64, thread_id: i32) -> Subscriber {
+    Subscriber {
+        chat_id,
+        thread_id: (thread_id != 0).then_some(thread_id),
+    }
+}
+
+fn follow(time_diff: Option<i64>, matched: bool) -> Follow {
+    Follow { time_diff, matched }
+}
+
+fn timestamp(secs: Option<i64>) -> Option<DateTime<Utc>> {
+    secs.and_then(|secs| DateTime::from_timestamp(secs, 0))
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        info!("applying sqlite migration {}", i + 1);
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn subscribers(&self) -> DbResult<Vec<Subscriber>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT chat_id, thread_id FROM subscribers ORDER BY chat_id, thread_id")?;
+        let ids = stmt
+            .query_map([], |row| Ok(subscriber(row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(ids)
+    }
+
+    fn subscribers_count(&self) -> DbResult<usize> {
+        let count = self
+            .conn()
+            .query_row("SELECT COUNT(*) FROM subscribers", [], |row| row.get(0))?;
+        Ok(count)
+    }
+
+    fn chat_subscribers(&self, chat_id: i64) -> DbResult<Vec<Subscriber>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT chat_id, thread_id FROM subscribers WHERE chat_id = ?1 ORDER BY thread_id",
+        )?;
+        let ids = stmt
+            .query_map([chat_id], |row| Ok(subscriber(row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(ids)
+    }
+
+    fn is_subscribed(&self, sub: Subscriber) -> DbResult<bool> {
+        let found = self
+            .conn()
+            .query_row(
+                "SELECT 1 FROM subscribers WHERE chat_id = ?1 AND thread_id = ?2",
+                params![sub.chat_id, thread_id(sub)],
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(found.is_some())
+    }
+
+    fn subscribe(&self, sub: Subscriber) -> DbResult<()> {
+        self.conn().execute(
+            "INSERT OR IGNORE INTO subscribers (chat_id, thread_id) VALUES (?1, ?2)",
+            params![sub.chat_id, thread_id(sub)],
+        )?;
+        Ok(())
+    }
+
+    fn unsubscribe(&self, sub: Subscriber) -> DbResult<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM subscribers WHERE chat_id = ?1 AND thread_id = ?2",
+            params![sub.chat_id, thread_id(sub)],
+        )?;
+        tx.execute(
+            "DELETE FROM notifications WHERE chat_id = ?1 AND thread_id = ?2",
+            params![sub.chat_id, thread_id(sub)],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn replace_chat_id(&self, old_chat_id: i64, new_chat_id: i64) -> DbResult<bool> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE OR REPLACE settings SET chat_id = ?2 WHERE chat_id = ?1",
+            [old_chat_id, new_chat_id],
+        )?;
+        let replaced = tx
+            .query_row(
+                "SELECT 1 FROM subscribers WHERE chat_id = ?1 LIMIT 1",
+                [old_chat_id],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if replaced {
+            tx.execute(
+                "UPDATE OR IGNORE subscribers SET chat_id = ?2 WHERE chat_id = ?1",
+                [old_chat_id, new_chat_id],
+            )?;
+            tx.execute("DELETE FROM subscribers WHERE chat_id = ?1", [old_chat_id])?;
+            tx.execute(
+                "UPDATE OR REPLACE notifications SET chat_id = ?2 WHERE chat_id = ?1",
+                [old_chat_id, new_chat_id],
+            )?;
+        }
+        tx.execute(
+            "UPDATE OR REPLACE follows SET chat_id = ?2 WHERE chat_id = ?1",
+            [old_chat_id, new_chat_id],
+        )?;
+        tx.commit()?;
+        Ok(replaced)
+    }
+
+    fn get_subscriber_info(&self, sub: Subscriber) -> DbResult<SubscriberInfo> {
+        let info = self
+            .conn()
+            .query_row(
+                "SELECT chat_type, title, username, subscribed_at, last_delivery, last_error,
+                        last_error_at, last_digest, pinned_message, pinned_launch, pinned_text
+                 FROM subscribers WHERE chat_id = ?1 AND thread_id = ?2",
+                params![sub.chat_id, thread_id(sub)],
+                |row| {
+                    Ok(SubscriberInfo {
+                        chat_type: ChatType::parse(&row.get::<_, String>(0)?),
+                        title: row.get(1)?,
+                        username: row.get(2)?,
+                        subscribed_at: timestamp(row.get(3)?),
+                        last_delivery: timestamp(row.get(4)?),
+                        last_error: row.get(5)?,
+                        last_error_at: timestamp(row.get(6)?),
+                        last_digest: timestamp(row.get(7)?),
+                        pinned_message: row.get(8)?,
+                        pinned_launch: row.get(9)?,
+                        pinned_text: row.get(10)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(info.unwrap_or_default())
+    }
+
+    fn set_subscriber_info(&self, sub: Subscriber, info: &SubscriberInfo) -> DbResult<()> {
+        self.conn().execute(
+            "UPDATE subscribers
+             SET chat_type = ?3, title = ?4, username = ?5, subscribed_at = ?6,
+                 last_delivery = ?7, last_error = ?8, last_error_at = ?9, last_digest = ?10,
+                 pinned_message = ?11, pinned_launch = ?12, pinned_text = ?13
+             WHERE chat_id = ?1 AND thread_id = ?2",
+            params![
+                sub.chat_id,
+                thread_id(sub),
+                info.chat_type.as_str(),
+                info.title,
+                info.username,
+                info.subscribed_at.map(|t| t.timestamp()),
+                info.last_delivery.map(|t| t.timestamp()),
+                info.last_error,
+                info.last_error_at.map(|t| t.timestamp()),
+                info.last_digest.map(|t| t.timestamp()),
+                info.pinned_message,
+                info.pinned_launch,
+                info.pinned_text,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_notified(&self, sub: Subscriber) -> DbResult<HashMap<u64, i64>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT launch_id, time_diff FROM notifications WHERE chat_id = ?1 AND thread_id = ?2",
+        )?;
+        let notified = stmt
+            .query_map(params![sub.chat_id, thread_id(sub)], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(notified)
+    }
+
+    fn add_notified(&self, sub: Subscriber, notified: &HashMap<u64, i64>) -> DbResult<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO notifications (chat_id, thread_id, launch_id, time_diff)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (launch_id, time_diff) in notified {
+                stmt.execute(params![sub.chat_id, thread_id(sub), launch_id, time_diff])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_settings(&self, sub: Subscriber) -> DbResult<ChatSettings> {
+        let data: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT data FROM settings WHERE chat_id = ?1 AND thread_id = ?2",
+                params![sub.chat_id, thread_id(sub)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let settings = match data {
+            Some(data) => serde_json::from_str(&data)?,
+            None => ChatSettings::default(),
+        };
+        Ok(settings)
+    }
+
+    fn set_settings(&self, sub: Subscriber, settings: &ChatSettings) -> DbResult<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO settings (chat_id, thread_id, data) VALUES (?1, ?2, ?3)",
+            params![
+                sub.chat_id,
+                thread_id(sub),
+                serde_json::to_string(settings)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn all_settings(&self) -> DbResult<Vec<(Subscriber, ChatSettings)>> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT chat_id, thread_id, data FROM settings ORDER BY chat_id, thread_id")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    subscriber(row.get(0)?, row.get(1)?),
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut all = vec![];
+        for (sub, data) in rows {
+            all.push((sub, serde_json::from_str(&data)?));
+        }
+        Ok(all)
+    }
+
+    fn get_follows(&self, sub: Subscriber) -> DbResult<Follows> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT launch_id, time_diff, matched FROM follows
+             WHERE chat_id = ?1 AND thread_id = ?2",
+        )?;
+        let follows = stmt
+            .query_map(params![sub.chat_id, thread_id(sub)], |row| {
+                Ok((row.get(0)?, follow(row.get(1)?, row.get(2)?)))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(follows)
+    }
+
+    fn set_follow(&self, sub: Subscriber, launch_id: u64, follow: Follow) -> DbResult<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO follows (chat_id, thread_id, launch_id, time_diff, matched)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                sub.chat_id,
+                thread_id(sub),
+                launch_id,
+                follow.time_diff,
+                follow.matched
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn unfollow(&self, sub: Subscriber, launch_id: u64) -> DbResult<bool> {
+        let removed = self.conn().execute(
+            "DELETE FROM follows WHERE chat_id = ?1 AND thread_id = ?2 AND launch_id = ?3",
+            params![sub.chat_id, thread_id(sub), launch_id],
+        )?;
+        Ok(removed > 0)
+    }
+
+    fn all_follows(&self) -> DbResult<Vec<(Subscriber, Follows)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT chat_id, thread_id, launch_id, time_diff, matched FROM follows
+             ORDER BY chat_id, thread_id",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut all: Vec<(Subscriber, Follows)> = vec![];
+        while let Some(row) = rows.next()? {
+            let sub = subscriber(row.get(0)?, row.get(1)?);
+            let (launch_id, follow) = (row.get(2)?, follow(row.get(3)?, row.get(4)?));
+            match all.last_mut() {
+                Some((last, follows)) if *last == sub => {
+                    follows.insert(launch_id, follow);
+                }
+                _ => all.push((sub, HashMap::from([(launch_id, follow)]))),
+            }
+        }
+        Ok(all)
+    }
+
+    fn followers(&self, launch_id: u64) -> DbResult<Vec<(Subscriber, Option<i64>)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT chat_id, thread_id, time_diff FROM follows WHERE launch_id = ?1
+             ORDER BY chat_id, thread_id",
+        )?;
+        let followers = stmt
+            .query_map([launch_id], |row| {
+                Ok((subscriber(row.get(0)?, row.get(1)?), row.get(2)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(followers)
+    }
+
+    fn set_launches(&self, launches: &[Launch]) -> DbResult<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM launches", [])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO launches (id, position, data) VALUES (?1, ?2, ?3)",
+            )?;
+            for (position, launch) in launches.iter().enumerate() {
+                stmt.execute(params![launch.id, position, serde_json::to_string(launch)?])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_launches(&self) -> DbResult<Vec<Launch>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT data FROM launches ORDER BY position")?;
+        let launches = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|data| serde_json::from_str(&data.ok()?).ok())
+            .collect();
+        Ok(launches)
+    }
+
+    fn add_event(&self, at: DateTime<Utc>, event: &Event) -> DbResult<()> {
+        self.conn().execute(
+            "INSERT INTO events (at, data) VALUES (?1, ?2)",
+            params![at.timestamp_millis(), serde_json::to_string(event)?],
+        )?;
+        Ok(())
+    }
+
+    fn events_since(&self, since: DateTime<Utc>) -> DbResult<Vec<(DateTime<Utc>, Event)>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT at, data FROM events WHERE at >= ?1 ORDER BY at, id")?;
+        let rows = stmt
+            .query_map([since.timestamp_millis()], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut events = vec![];
+        for (at, data) in rows {
+            let Some(at) = DateTime::from_timestamp_millis(at) else {
+                continue;
+            };
+            events.push((at, serde_json::from_str(&data)?));
+        }
+        Ok(events)
+    }
+
+    fn prune_events(&self, before: DateTime<Utc>) -> DbResult<usize> {
+        let removed = self.conn().execute(
+            "DELETE FROM events WHERE at < ?1",
+            [before.timestamp_millis()],
+        )?;
+        Ok(removed)
+    }
+
+    fn get_meta(&self, key: &str) -> DbResult<Option<String>> {
+        let value = self
+            .conn()
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(value)
+    }
+
+    fn set_meta(&self, key: &str, value: &str) -> DbResult<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            [key, value],
+        )?;
+        Ok(())
+    }
+
+    fn all_meta(&self) -> DbResult<HashMap<String, String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT key, value FROM meta")?;
+        let all = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(all)
+    }
+
+    fn admins(&self) -> DbResult<Vec<(i64, AdminRole)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT chat_id, role FROM admins ORDER BY chat_id")?;
+        let admins = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?
+            .filter_map(|row| {
+                let (chat_id, role) = row.ok()?;
+                Some((chat_id, AdminRole::parse(&role)?))
+            })
+            .collect();
+        Ok(admins)
+    }
+
+    fn admin_role(&self, chat_id: i64) -> DbResult<Option<AdminRole>> {
+        let role: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT role FROM admins WHERE chat_id = ?1",
+                [chat_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(role.as_deref().and_then(AdminRole::parse))
+    }
+
+    fn set_admin(&self, chat_id: i64, role: AdminRole) -> DbResult<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO admins (chat_id, role) VALUES (?1, ?2)",
+            params![chat_id, role.as_str()],
+        )?;
+        Ok(())
+    }
+
+    fn remove_admin(&self, chat_id: i64) -> DbResult<bool> {
+        let removed = self
+            .conn()
+            .execute("DELETE FROM admins WHERE chat_id = ?1", [chat_id])?;
+        Ok(removed > 0)
+    }
+
+    fn add_audit(&self, entry: &AuditEntry) -> DbResult<()> {
+        self.conn().execute(
+            "INSERT INTO audit (at, chat_id, user_id, username, action)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                entry.at.timestamp_millis(),
+                entry.chat_id,
+                entry.user_id,
+                entry.username,
+                entry.action,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn audit_log(&self, limit: usize) -> DbResult<Vec<AuditEntry>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT at, chat_id, user_id, username, action FROM audit
+             ORDER BY at DESC, id DESC LIMIT ?1",
+        )?;
+        let entries = stmt
+            .query_map([i64::try_from(limit).unwrap_or(i64::MAX)], |row| {
+                Ok(AuditEntry {
+                    at: DateTime::from_timestamp_millis(row.get(0)?).unwrap_or_default(),
+                    chat_id: row.get(1)?,
+                    user_id: row.get(2)?,
+                    username: row.get(3)?,
+                    action: row.get(4)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(entries)
+    }
+
+    fn validate(&self) -> DbResult<Vec<String>> {
+        let conn = self.conn();
+        let mut problems = vec![];
+
+        let mut stmt = conn.prepare("SELECT chat_id, thread_id, data FROM settings")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let sub = subscriber(row.get(0)?, row.get(1)?);
+            if let Err(err) = serde_json::from_str::<ChatSettings>(&row.get::<_, String>(2)?) {
+                problems.push(format!("settings of {}: {}", sub, err));
+            }
+        }
+
+        let mut stmt = conn.prepare("SELECT id, data FROM launches")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            if let Err(err) = serde_json::from_str::<Launch>(&row.get::<_, String>(1)?) {
+                problems.push(format!("launch {}: {}", id, err));
+            }
+        }
+
+        let mut stmt = conn.prepare("SELECT id, data FROM events")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            if let Err(err) = serde_json::from_str::<Event>(&row.get::<_, String>(1)?) {
+                problems.push(format!("event {}: {}", id, err));
+            }
+        }
+
+        let mut stmt = conn.prepare("SELECT chat_id, role FROM admins")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let chat_id: i64 = row.get(0)?;
+            let role: String = row.get(1)?;
+            if AdminRole::parse(&role).is_none() {
+                problems.push(format!(
+                    "admin role of {}: unknown role {:?}",
+                    chat_id, role
+                ));
+            }
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT n.chat_id, n.thread_id FROM notifications n
+             LEFT JOIN subscribers s ON s.chat_id = n.chat_id AND s.thread_id = n.thread_id
+             WHERE s.chat_id IS NULL",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let sub = subscriber(row.get(0)?, row.get(1)?);
+            problems.push(format!("notifications of unsubscribed chat {}", sub));
+        }
+
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn migrate_from_v1() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("db.sqlite");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(MIGRATIONS[0]).unwrap();
+            conn.pragma_update(None, "user_version", 1).unwrap();
+            conn.execute_batch(
+                r#"
+                INSERT INTO subscribers (chat_id) VALUES (1);
+                INSERT INTO notifications (chat_id, launch_id, time_diff) VALUES (1, 10, 3600);
+                INSERT INTO settings (chat_id, data) VALUES (1, '{"language":"ru"}');
+            "#,
+            )
+            .unwrap();
+        }
+
+        let db = SqliteStorage::open(&path).unwrap();
+        let version: usize = db
+            .conn()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        let sub = Subscriber::chat(1);
+        assert_eq!(db.subscribers().unwrap(), [sub]);
+        assert_eq!(db.get_notified(sub).unwrap(), HashMap::from([(10, 3600)]));
+        assert_eq!(
+            db.get_settings(sub).unwrap().language,
+            Some(crate::i18n::Lang::Ru)
+        );
+        assert_eq!(
+            db.get_subscriber_info(sub).unwrap(),
+            SubscriberInfo::default()
+        );
+        assert!(db.get_follows(sub).unwrap().is_empty());
+    }
+}
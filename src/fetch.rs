@@ -1,14 +1,22 @@
 use chrono::{Duration, DurationRound, TimeDelta, Utc};
+use tokio::sync::{mpsc, oneshot};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info};
 
 use crate::{
-    bot::{launches_notify, MyBot},
+    bot::{digests_notify, launches_notify, pins_update, MyBot},
     db::Db,
     types::{Launch, Launches, RLError},
 };
 
-const API_URL: &str = "https://fdo.rocketlaunch.live/json/launches/next/5";
+pub const API_URL: &str = "https://fdo.rocketlaunch.live/json/launches/next/5";
+
+/// Events older than that are of no use for statistics.
+const EVENTS_RETENTION_DAYS: i64 = 31;
+
+/// Asks worker to fetch launches right away, answering with number of fetched
+/// launches or fetch error.
+pub type RefreshRequest = oneshot::Sender<Result<usize, String>>;
 
 #[tracing::instrument]
 pub async fn fetch() -> Result<Vec<Launch>, RLError> {
@@ -19,19 +27,29 @@ pub async fn fetch() -> Result<Vec<Launch>, RLError> {
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn worker(db: Db, bot: MyBot, cancellation: CancellationToken) {
+pub async fn worker(
+    db: Db,
+    bot: MyBot,
+    cancellation: CancellationToken,
+    mut refresh: mpsc::Receiver<RefreshRequest>,
+) {
+    let mut pending = vec![];
     loop {
-        match worker_loop(&db, &bot, &cancellation).await {
+        match worker_loop(&db, &bot, &cancellation, &mut refresh, pending).await {
             Ok(()) => {
                 return;
             }
             Err(err) => {
                 error!("worker_loop fail: {}", err);
+                pending = vec![];
                 tokio::select! {
                     _ = cancellation.cancelled() => {
                         return;
                     }
                     _ = tokio::time::sleep(tokio::time::Duration::from_secs(60)) => {}
+                    Some(request) = refresh.recv() => {
+                        pending.push(request);
+                    }
                 }
             }
         }
@@ -43,11 +61,21 @@ async fn worker_loop(
     db: &Db,
     bot: &MyBot,
     cancellation: &CancellationToken,
+    refresh: &mut mpsc::Receiver<RefreshRequest>,
+    mut pending: Vec<RefreshRequest>,
 ) -> Result<(), RLError> {
     loop {
-        let launches = fetch().await?;
-        db.set_launches(&launches)?;
+        let fetched = update_launches(db).await;
+        for request in pending.drain(..) {
+            let _ = request.send(match &fetched {
+                Ok(launches) => Ok(launches.len()),
+                Err(err) => Err(err.to_string()),
+            });
+        }
+        let launches = fetched?;
         launches_notify(bot, db, &launches).await?;
+        digests_notify(bot, db, &launches).await?;
+        pins_update(bot, db, &launches).await?;
 
         let next_run_in: Duration = {
             let next_min = (Utc::now() + Duration::try_minutes(1).unwrap())
@@ -63,6 +91,19 @@ async fn worker_loop(
                 return Ok(());
             }
             _ = tokio::time::sleep(next_run_in.to_std().unwrap()) => {}
+            Some(request) = refresh.recv() => {
+                info!("refresh requested");
+                pending.push(request);
+            }
         }
     }
 }
+
+async fn update_launches(db: &Db) -> Result<Vec<Launch>, RLError> {
+    let launches = fetch().await?;
+    db.set_launches(&launches)?;
+    db.follow_matching_launches(&launches)?;
+    db.set_last_fetch(Utc::now())?;
+    db.prune_events(Utc::now() - Duration::try_days(EVENTS_RETENTION_DAYS).unwrap())?;
+    Ok(launches)
+}
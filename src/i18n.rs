@@ -0,0 +1,496 @@
+use std::time::Duration;
+
+use chrono::Weekday;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    Ru,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::En, Lang::Ru];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::Ru => "ru",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lang| lang.as_str() == s)
+    }
+
+    /// Language of IETF tag Telegram reports for users, like `en-US`.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let primary = code.split(['-', '_']).next()?.to_lowercase();
+        Self::parse(&primary)
+    }
+
+    pub fn catalog(self) -> &'static Catalog {
+        match self {
+            Lang::En => &EN,
+            Lang::Ru => &RU,
+        }
+    }
+}
+
+/// Texts of bot replies and notifications in one language. They're plain,
+/// escaping them for MarkdownV2 is up to callers; `{}` stands for a value
+/// filled in by them.
+pub struct Catalog {
+    /// Name of the language in itself.
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub commands: &'static str,
+    /// Command name to description, commands missing are described in
+    /// English.
+    pub command_descriptions: &'static [(&'static str, &'static str)],
+    pub admins_only: &'static str,
+    pub groups_only: &'static str,
+    pub subscribed: &'static str,
+    pub unsubscribed: &'static str,
+    pub error_subscribing: &'static str,
+    pub error_unsubscribing: &'static str,
+    pub error_getting_settings: &'static str,
+    pub error_saving_settings: &'static str,
+    pub suborbital_excluded: &'static str,
+    pub suborbital_included: &'static str,
+    pub everyone_manages: &'static str,
+    pub admins_manage: &'static str,
+    pub language_set: &'static str,
+    /// Goes before countdown to T-0, as in `in 5h`.
+    pub countdown_in: &'static str,
+    /// Units of durations: days, hours, minutes and seconds.
+    pub duration_units: [&'static str; 4],
+    /// Short weekday names, from Monday.
+    pub weekdays: [&'static str; 7],
+    pub suborbital: &'static str,
+    pub topic: &'static str,
+    pub error_getting_launch: &'static str,
+    pub error_getting_launches: &'static str,
+    /// Titles of `/settings` and its labels.
+    pub settings: &'static str,
+    pub topic_settings: &'static str,
+    pub yes: &'static str,
+    pub no: &'static str,
+    pub subscribed_label: &'static str,
+    pub notify_before: &'static str,
+    pub silent_before: &'static str,
+    pub quiet_hours: &'static str,
+    pub language: &'static str,
+    pub digest: &'static str,
+    pub next_launch_label: &'static str,
+    pub pinned: &'static str,
+    pub suborbital_launches: &'static str,
+    pub excluded: &'static str,
+    pub followed_launches: &'static str,
+    pub followed_vehicles: &'static str,
+    pub followed_tags: &'static str,
+    pub muted_tags: &'static str,
+    pub muted_launches: &'static str,
+    pub managed_by: &'static str,
+    pub managed_by_everyone: &'static str,
+    pub managed_by_admins: &'static str,
+    /// Launch details of `/launch`.
+    pub date: &'static str,
+    pub window: &'static str,
+    pub pad: &'static str,
+    pub mission: &'static str,
+    pub tags: &'static str,
+    pub updated: &'static str,
+    /// Goes after time passed, as in `5h ago`.
+    pub ago: &'static str,
+    pub no_such_launch: &'static str,
+    pub button_previous: &'static str,
+    pub button_next: &'static str,
+    pub button_mute: &'static str,
+    pub button_unmute: &'static str,
+    pub button_follow: &'static str,
+    pub button_unfollow: &'static str,
+    pub launch_muted: &'static str,
+    pub launch_unmuted: &'static str,
+    pub launch_followed: &'static str,
+    pub launch_unfollowed: &'static str,
+    pub following_launch: &'static str,
+    pub matched_launch_unfollow: &'static str,
+    pub not_following_launch: &'static str,
+    pub launch_not_followed: &'static str,
+    pub error_following_launch: &'static str,
+    pub error_unfollowing_launch: &'static str,
+    pub or: &'static str,
+    /// Replies of `/find`.
+    pub found: &'static str,
+    pub and_more: &'static str,
+    pub no_launches_found: &'static str,
+    pub filter_empty_value: &'static str,
+    pub filter_unknown: &'static str,
+    pub filter_bad_duration: &'static str,
+    /// Replies of `/tags` and `/tag`.
+    pub tags_title: &'static str,
+    pub no_tags: &'static str,
+    pub tag_followed: &'static str,
+    pub tag_unfollowed: &'static str,
+    pub tag_muted: &'static str,
+    pub tag_unmuted: &'static str,
+    /// Replies of `/follow_vehicle` and `/unfollow_vehicle`.
+    pub vehicles_title: &'static str,
+    pub vehicle_followed: &'static str,
+    pub vehicle_unfollowed: &'static str,
+    pub not_a_vehicle: &'static str,
+    /// Replies of `/timezone`, `/quiet_hours` and `/reminder_sound`.
+    pub timezone_usage: &'static str,
+    pub timezone_is: &'static str,
+    pub timezone_set: &'static str,
+    pub timezone_unknown: &'static str,
+    pub quiet_hours_help: &'static str,
+    pub quiet_hours_set: &'static str,
+    pub quiet_hours_off: &'static str,
+    pub lead_times: &'static str,
+    pub reminders_loud: &'static str,
+    pub reminders_silent: &'static str,
+    /// Replies of `/digest` and digests themselves.
+    pub digest_help: &'static str,
+    pub digest_set: &'static str,
+    /// Digest schedules, `{}` are the weekday and time.
+    pub digest_daily: &'static str,
+    pub digest_weekly: &'static str,
+    pub digest_set_unsubscribed: &'static str,
+    pub digest_off: &'static str,
+    pub digest_day: &'static str,
+    pub digest_week: &'static str,
+    pub digest_day_empty: &'static str,
+    pub digest_week_empty: &'static str,
+    /// Replies of `/pin_next` and the pinned message.
+    pub next_launch: &'static str,
+    pub no_upcoming_launches: &'static str,
+    pub pin_on: &'static str,
+    pub pin_on_unsubscribed: &'static str,
+    pub pin_off: &'static str,
+    pub error_pinning: &'static str,
+}
+
+impl Catalog {
+    /// `1d 2h 15m`, down to seconds only when there are some.
+    pub fn duration(&self, duration: Duration) -> String {
+        let secs = duration.as_secs();
+        let [days, hours, minutes, seconds] = self.duration_units;
+        let parts = [
+            (secs / 86400, days),
+            (secs / 3600 % 24, hours),
+            (secs / 60 % 60, minutes),
+            (secs % 60, seconds),
+        ];
+        let text = parts
+            .iter()
+            .filter(|(n, _)| *n > 0)
+            .map(|(n, unit)| format!("{}{}", n, unit))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if text.is_empty() {
+            format!("0{}", minutes)
+        } else {
+            text
+        }
+    }
+
+    pub fn weekday(&self, weekday: Weekday) -> &'static str {
+        self.weekdays[weekday.num_days_from_monday() as usize]
+    }
+}
+
+static EN: Catalog = Catalog {
+    name: "English",
+    usage: "Usage",
+    commands: "Commands:",
+    command_descriptions: &[],
+    admins_only: "Only group administrators can do that",
+    groups_only: "Only makes sense in groups",
+    subscribed: "Subscribed, standby for notifications!",
+    unsubscribed: "Unsubscribed",
+    error_subscribing: "Error subscribing",
+    error_unsubscribing: "Error unsubscribing",
+    error_getting_settings: "Error getting settings",
+    error_saving_settings: "Error saving settings",
+    suborbital_excluded: "Suborbital launches are excluded now",
+    suborbital_included: "Suborbital launches are included now",
+    everyone_manages: "Everyone can manage subscription now",
+    admins_manage: "Only group administrators can manage subscription now",
+    language_set: "Talking English now",
+    countdown_in: "in",
+    duration_units: ["d", "h", "m", "s"],
+    weekdays: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    suborbital: "suborbital",
+    topic: "topic",
+    error_getting_launch: "Error getting launch",
+    error_getting_launches: "Error getting launches",
+    settings: "Settings",
+    topic_settings: "Settings of this topic",
+    yes: "yes",
+    no: "no",
+    subscribed_label: "subscribed",
+    notify_before: "notify before",
+    silent_before: "silent before",
+    quiet_hours: "quiet hours",
+    language: "language",
+    digest: "digest",
+    next_launch_label: "next launch",
+    pinned: "pinned",
+    suborbital_launches: "suborbital launches",
+    excluded: "excluded",
+    followed_launches: "followed launches",
+    followed_vehicles: "followed vehicles",
+    followed_tags: "followed tags",
+    muted_tags: "muted tags",
+    muted_launches: "muted launches",
+    managed_by: "managed by",
+    managed_by_everyone: "everyone",
+    managed_by_admins: "group administrators",
+    date: "Date",
+    window: "Window",
+    pad: "Pad",
+    mission: "Mission",
+    tags: "Tags",
+    updated: "updated",
+    ago: "ago",
+    no_such_launch: "No such launch",
+    button_previous: "◀ Previous",
+    button_next: "Next ▶",
+    button_mute: "🔕 Mute",
+    button_unmute: "🔔 Unmute",
+    button_follow: "Follow",
+    button_unfollow: "Unfollow",
+    launch_muted: "Muted, no notifications about this launch",
+    launch_unmuted: "Unmuted",
+    launch_followed: "Following, you'll be notified about this launch",
+    launch_unfollowed: "Not following anymore",
+    following_launch: "Following {}, notifications will come even without /start",
+    matched_launch_unfollow: "Launches of followed vehicles and tags get followed again, mute the launch instead",
+    not_following_launch: "Not following {} anymore",
+    launch_not_followed: "Launch wasn't followed",
+    error_following_launch: "Error following launch",
+    error_unfollowing_launch: "Error unfollowing launch",
+    or: "or",
+    found: "Found {}",
+    and_more: "…and {} more",
+    no_launches_found: "No launches found",
+    filter_empty_value: "Empty value of {}",
+    filter_unknown: "Unknown filter {}",
+    filter_bad_duration: "Bad duration {}",
+    tags_title: "Tags of cached launches",
+    no_tags: "No tags in cached launches",
+    tag_followed: "Following every launch tagged {}",
+    tag_unfollowed: "Not following launches tagged {}",
+    tag_muted: "No notifications about launches tagged {}",
+    tag_unmuted: "Notifications are back for launches tagged {}",
+    vehicles_title: "Vehicles of cached launches",
+    vehicle_followed: "Following every launch of {}, even without /start",
+    vehicle_unfollowed: "Not following launches of {} anymore",
+    not_a_vehicle: "Not a vehicle slug or name",
+    timezone_usage: "Usage: {}, e.g. {}",
+    timezone_is: "Timezone is {}",
+    timezone_set: "Timezone is {} now",
+    timezone_unknown: "Unknown timezone, use names like Europe/Berlin or UTC",
+    quiet_hours_help: "Notifications due in quiet hours are delayed until they end, sent without sound or dropped. Hours are in the chat timezone, see /timezone",
+    quiet_hours_set: "Quiet hours are {} now",
+    quiet_hours_off: "Quiet hours are off now",
+    lead_times: "Lead times",
+    reminders_loud: "Reminders {} before launch are loud now",
+    reminders_silent: "Reminders {} before launch are silent now",
+    digest_help: "Digest lists launches of the next 24 hours or 7 days instead of notifying about each of them. Time is in the chat timezone, see /timezone",
+    digest_set: "Digest is sent {} now, instead of notifications",
+    digest_daily: "daily at {}",
+    digest_weekly: "weekly on {} at {}",
+    digest_set_unsubscribed: "Digest is sent {} once subscribed with /start",
+    digest_off: "Digest is off, notifying about each launch now",
+    digest_day: "Launches in the next 24 hours",
+    digest_week: "Launches in the next 7 days",
+    digest_day_empty: "No launches in the next 24 hours",
+    digest_week_empty: "No launches in the next 7 days",
+    next_launch: "Next launch",
+    no_upcoming_launches: "No upcoming launches",
+    pin_on: "Next launch is pinned now, the bot needs a right to pin messages in groups",
+    pin_on_unsubscribed: "Next launch gets pinned once subscribed with /start",
+    pin_off: "Not pinning next launch anymore",
+    error_pinning: "Error pinning next launch",
+};
+
+static RU: Catalog = Catalog {
+    name: "Русский",
+    usage: "Использование",
+    commands: "Команды:",
+    command_descriptions: &[
+        ("id", "id текущего чата"),
+        (
+            "start",
+            "подписаться на уведомления о запусках, на форумах для каждой темы отдельно",
+        ),
+        ("stop", "отписаться от уведомлений о запусках"),
+        ("launches", "показать запуски"),
+        ("next", "показать ближайший запуск"),
+        (
+            "launch",
+            "подробности запуска по id или slug, по умолчанию ближайшего",
+        ),
+        (
+            "follow",
+            "получать уведомления об одном запуске по id или slug",
+        ),
+        ("unfollow", "перестать следить за запуском"),
+        (
+            "follow_vehicle",
+            "получать уведомления о каждом запуске ракеты, без аргументов список ракет",
+        ),
+        ("unfollow_vehicle", "перестать следить за ракетой"),
+        ("tags", "теги известных запусков"),
+        (
+            "tag",
+            "следить, перестать следить, заглушить или вернуть запуски по тегу",
+        ),
+        (
+            "find",
+            "найти запуски, например /find provider:spacex country:us tag:crew next:7d",
+        ),
+        ("settings", "показать настройки чата"),
+        (
+            "suborbital",
+            "включить или исключить суборбитальные запуски",
+        ),
+        (
+            "reminder_sound",
+            "напоминания за заданное время со звуком или без, например /reminder_sound 24h silent",
+        ),
+        ("timezone", "часовой пояс чата, например Europe/Moscow"),
+        (
+            "quiet_hours",
+            "тихие часы, например /quiet_hours 23:00-07:00 delay|silent|drop, или off",
+        ),
+        (
+            "digest",
+            "сводка запусков вместо уведомлений, например /digest daily 08:00, или off",
+        ),
+        (
+            "pin_next",
+            "держать ближайший запуск закреплённым: on или off",
+        ),
+        ("language", "язык бота: en или ru"),
+        (
+            "group_control",
+            "кто управляет подпиской группы: admins или everyone",
+        ),
+        ("help", "помощь"),
+    ],
+    admins_only: "Это могут только администраторы группы",
+    groups_only: "Имеет смысл только в группах",
+    subscribed: "Подписка оформлена, ждите уведомлений!",
+    unsubscribed: "Подписка отменена",
+    error_subscribing: "Ошибка подписки",
+    error_unsubscribing: "Ошибка отмены подписки",
+    error_getting_settings: "Ошибка чтения настроек",
+    error_saving_settings: "Ошибка сохранения настроек",
+    suborbital_excluded: "Суборбитальные запуски теперь исключены",
+    suborbital_included: "Суборбитальные запуски теперь включены",
+    everyone_manages: "Теперь подпиской могут управлять все",
+    admins_manage: "Теперь подпиской могут управлять только администраторы группы",
+    language_set: "Теперь бот говорит по-русски",
+    countdown_in: "через",
+    duration_units: ["д", "ч", "мин", "с"],
+    weekdays: ["пн", "вт", "ср", "чт", "пт", "сб", "вс"],
+    suborbital: "суборбитальный",
+    topic: "тема",
+    error_getting_launch: "Ошибка получения запуска",
+    error_getting_launches: "Ошибка получения запусков",
+    settings: "Настройки",
+    topic_settings: "Настройки этой темы",
+    yes: "да",
+    no: "нет",
+    subscribed_label: "подписка",
+    notify_before: "уведомлять за",
+    silent_before: "без звука за",
+    quiet_hours: "тихие часы",
+    language: "язык",
+    digest: "сводка",
+    next_launch_label: "ближайший запуск",
+    pinned: "закреплён",
+    suborbital_launches: "суборбитальные запуски",
+    excluded: "исключены",
+    followed_launches: "отслеживаемые запуски",
+    followed_vehicles: "отслеживаемые ракеты",
+    followed_tags: "отслеживаемые теги",
+    muted_tags: "заглушённые теги",
+    muted_launches: "заглушённые запуски",
+    managed_by: "управляют",
+    managed_by_everyone: "все",
+    managed_by_admins: "администраторы группы",
+    date: "Дата",
+    window: "Окно",
+    pad: "Площадка",
+    mission: "Миссия",
+    tags: "Теги",
+    updated: "обновлено",
+    ago: "назад",
+    no_such_launch: "Нет такого запуска",
+    button_previous: "◀ Назад",
+    button_next: "Далее ▶",
+    button_mute: "🔕 Заглушить",
+    button_unmute: "🔔 Вернуть",
+    button_follow: "Следить",
+    button_unfollow: "Не следить",
+    launch_muted: "Заглушено, уведомлений об этом запуске не будет",
+    launch_unmuted: "Уведомления возвращены",
+    launch_followed: "Слежу, уведомления об этом запуске придут",
+    launch_unfollowed: "Больше не слежу",
+    following_launch: "Слежу за {}, уведомления придут и без /start",
+    matched_launch_unfollow: "За запусками отслеживаемых ракет и тегов слежка возобновится, лучше заглушите запуск",
+    not_following_launch: "Больше не слежу за {}",
+    launch_not_followed: "За запуском не следили",
+    error_following_launch: "Ошибка слежения за запуском",
+    error_unfollowing_launch: "Ошибка отмены слежения за запуском",
+    or: "или",
+    found: "Найдено: {}",
+    and_more: "…и ещё {}",
+    no_launches_found: "Запуски не найдены",
+    filter_empty_value: "Пустое значение {}",
+    filter_unknown: "Неизвестный фильтр {}",
+    filter_bad_duration: "Неверная длительность {}",
+    tags_title: "Теги загруженных запусков",
+    no_tags: "В загруженных запусках нет тегов",
+    tag_followed: "Слежу за каждым запуском с тегом {}",
+    tag_unfollowed: "Больше не слежу за запусками с тегом {}",
+    tag_muted: "Уведомлений о запусках с тегом {} не будет",
+    tag_unmuted: "Уведомления о запусках с тегом {} возвращены",
+    vehicles_title: "Ракеты загруженных запусков",
+    vehicle_followed: "Слежу за каждым запуском {}, даже без /start",
+    vehicle_unfollowed: "Больше не слежу за запусками {}",
+    not_a_vehicle: "Нет ракеты с таким slug или названием",
+    timezone_usage: "Использование: {}, например {}",
+    timezone_is: "Часовой пояс {}",
+    timezone_set: "Теперь часовой пояс {}",
+    timezone_unknown: "Неизвестный часовой пояс, используйте названия вроде Europe/Moscow или UTC",
+    quiet_hours_help: "Уведомления в тихие часы откладываются до их конца, приходят без звука или пропускаются. Время указывается в часовом поясе чата, см. /timezone",
+    quiet_hours_set: "Теперь тихие часы {}",
+    quiet_hours_off: "Тихие часы выключены",
+    lead_times: "Время напоминаний",
+    reminders_loud: "Напоминания за {} до запуска теперь со звуком",
+    reminders_silent: "Напоминания за {} до запуска теперь без звука",
+    digest_help: "Сводка перечисляет запуски ближайших 24 часов или 7 дней вместо уведомлений о каждом. Время указывается в часовом поясе чата, см. /timezone",
+    digest_daily: "ежедневно в {}",
+    digest_weekly: "еженедельно, {} в {}",
+    digest_set: "Теперь вместо уведомлений приходит сводка: {}",
+    digest_set_unsubscribed: "Сводка будет приходить после подписки через /start: {}",
+    digest_off: "Сводка выключена, теперь уведомления о каждом запуске",
+    digest_day: "Запуски в ближайшие 24 часа",
+    digest_week: "Запуски в ближайшие 7 дней",
+    digest_day_empty: "В ближайшие 24 часа запусков нет",
+    digest_week_empty: "В ближайшие 7 дней запусков нет",
+    next_launch: "Ближайший запуск",
+    no_upcoming_launches: "Нет предстоящих запусков",
+    pin_on: "Ближайший запуск теперь закреплён, в группах боту нужно право закреплять сообщения",
+    pin_on_unsubscribed: "Ближайший запуск будет закреплён после подписки через /start",
+    pin_off: "Ближайший запуск больше не закрепляется",
+    error_pinning: "Ошибка закрепления ближайшего запуска",
+};
@@ -2,4 +2,6 @@ pub mod bot;
 pub mod config;
 pub mod db;
 pub mod fetch;
+pub mod i18n;
+pub mod template;
 pub mod types;
@@ -1,5 +1,6 @@
 use clap::Parser;
-use rocketlaunch_bot::{bot::init_bot, config::Args, db::Db, fetch::worker};
+use rocketlaunch_bot::{bot::init_bot, config::Args, db, fetch::worker};
+use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
@@ -15,11 +16,12 @@ async fn main() {
 
     let (cancellation, force_stop) = spawn_shutdown();
 
-    let db = Db::open("db").expect("failed opening db");
+    let db = db::open(config.storage, config.db_path()).expect("failed opening db");
 
-    let (bot, mut bot_dispatcher) = init_bot(config.bot, db.clone()).await;
+    let (refresh_tx, refresh_rx) = mpsc::channel(8);
+    let (bot, mut bot_dispatcher) = init_bot(config.bot, db.clone(), refresh_tx).await;
 
-    let worker = tokio::spawn(worker(db, bot, cancellation.clone()));
+    let worker = tokio::spawn(worker(db, bot, cancellation.clone(), refresh_rx));
     let dispatcher_handle = tokio::spawn(async move {
         tokio::select! {
             _ = bot_dispatcher.dispatch() => (),
@@ -0,0 +1,390 @@
+use std::{fmt, str::FromStr};
+
+use serde::Deserialize;
+use teloxide::utils::markdown;
+
+/// Launch detail a template placeholder stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Provider,
+    Vehicle,
+    Mission,
+    Pad,
+    /// Launch window, or T-0 when window isn't known.
+    Window,
+    /// Time left until T-0.
+    Countdown,
+    Link,
+}
+
+impl Field {
+    pub const ALL: [Field; 7] = [
+        Field::Provider,
+        Field::Vehicle,
+        Field::Mission,
+        Field::Pad,
+        Field::Window,
+        Field::Countdown,
+        Field::Link,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Field::Provider => "provider",
+            Field::Vehicle => "vehicle",
+            Field::Mission => "mission",
+            Field::Pad => "pad",
+            Field::Window => "window",
+            Field::Countdown => "countdown",
+            Field::Link => "link",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.as_str() == s)
+    }
+}
+
+/// Where a placeholder is, deciding how its value gets escaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Text,
+    Code,
+    /// Inside `(...)` of an inline link.
+    Url,
+}
+
+/// Formatting entity opened in text and not closed yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Entity {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    /// `[...]` of an inline link.
+    LinkText,
+}
+
+impl Entity {
+    fn name(self) -> &'static str {
+        match self {
+            Entity::Bold => "bold",
+            Entity::Italic => "italic",
+            Entity::Underline => "underline",
+            Entity::Strikethrough => "strikethrough",
+            Entity::LinkText => "link",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Placeholder(Field, Context),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TemplateError {
+    #[error("unknown placeholder {{{0}}}")]
+    UnknownPlaceholder(String),
+
+    #[error("placeholder at {0} isn't closed with }}")]
+    UnclosedPlaceholder(usize),
+
+    #[error("{0:?} at {1} has to be escaped as \\{0}")]
+    Unescaped(char, usize),
+
+    #[error("template ends inside {0}")]
+    UnclosedEntity(&'static str),
+
+    #[error("{0:?} at {1} closes {2} before entities opened inside it")]
+    Misnested(char, usize, &'static str),
+
+    #[error("template ends with a lone backslash")]
+    TrailingBackslash,
+}
+
+/// Notification text in MarkdownV2 with `{field}` placeholders, as in
+/// `*{provider} {vehicle}* in {countdown}`. Literal text is sent as is, so
+/// characters MarkdownV2 reserves have to be escaped with `\`, values of
+/// placeholders get escaped as their place in the text requires.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+/// Reserved by MarkdownV2 outside of entities they're part of. Blockquotes
+/// and spoilers aren't supported, so `>` and `|` are always reserved.
+const RESERVED: &[char] = &[
+    '#', '+', '-', '=', '{', '}', '.', '!', '(', ')', ']', '>', '|',
+];
+
+/// Opens `entity`, or closes it if it's the innermost one open.
+fn toggle(
+    entities: &mut Vec<Entity>,
+    entity: Entity,
+    c: char,
+    i: usize,
+) -> Result<(), TemplateError> {
+    if entities.last() == Some(&entity) {
+        entities.pop();
+    } else if entities.contains(&entity) {
+        return Err(TemplateError::Misnested(c, i, entity.name()));
+    } else {
+        entities.push(entity);
+    }
+    Ok(())
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut context = Context::Text;
+        let mut entities = vec![];
+        let mut chars = source.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let (_, escaped) = chars.next().ok_or(TemplateError::TrailingBackslash)?;
+                    literal.push(c);
+                    literal.push(escaped);
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, c)) => name.push(c),
+                            None => return Err(TemplateError::UnclosedPlaceholder(i)),
+                        }
+                    }
+                    let field =
+                        Field::parse(&name).ok_or(TemplateError::UnknownPlaceholder(name))?;
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    parts.push(Part::Placeholder(field, context));
+                }
+                '`' => {
+                    context = match context {
+                        Context::Text => Context::Code,
+                        Context::Code => Context::Text,
+                        Context::Url => return Err(TemplateError::Unescaped(c, i)),
+                    };
+                    literal.push(c);
+                }
+                '*' if context == Context::Text => {
+                    toggle(&mut entities, Entity::Bold, c, i)?;
+                    literal.push(c);
+                }
+                '~' if context == Context::Text => {
+                    toggle(&mut entities, Entity::Strikethrough, c, i)?;
+                    literal.push(c);
+                }
+                '_' if context == Context::Text => {
+                    if chars.next_if(|&(_, c)| c == '_').is_some() {
+                        toggle(&mut entities, Entity::Underline, c, i)?;
+                        literal.push_str("__");
+                    } else {
+                        toggle(&mut entities, Entity::Italic, c, i)?;
+                        literal.push(c);
+                    }
+                }
+                '[' if context == Context::Text => {
+                    // links can't be nested
+                    if entities.contains(&Entity::LinkText) {
+                        return Err(TemplateError::Unescaped(c, i));
+                    }
+                    entities.push(Entity::LinkText);
+                    literal.push(c);
+                }
+                ']' if context == Context::Text
+                    && entities.contains(&Entity::LinkText)
+                    && chars.peek().is_some_and(|&(_, c)| c == '(') =>
+                {
+                    if entities.pop() != Some(Entity::LinkText) {
+                        return Err(TemplateError::Misnested(c, i, Entity::LinkText.name()));
+                    }
+                    chars.next();
+                    context = Context::Url;
+                    literal.push_str("](");
+                }
+                ')' if context == Context::Url => {
+                    context = Context::Text;
+                    literal.push(c);
+                }
+                c if context == Context::Text && RESERVED.contains(&c) => {
+                    return Err(TemplateError::Unescaped(c, i));
+                }
+                c => literal.push(c),
+            }
+        }
+        match context {
+            Context::Text => {}
+            Context::Code => return Err(TemplateError::UnclosedEntity("code")),
+            Context::Url => return Err(TemplateError::UnclosedEntity("link")),
+        }
+        if let Some(entity) = entities.last() {
+            return Err(TemplateError::UnclosedEntity(entity.name()));
+        }
+        parts.push(Part::Literal(literal));
+        parts.retain(|part| part != &Part::Literal(String::new()));
+        Ok(Self {
+            source: source.to_string(),
+            parts,
+        })
+    }
+
+    /// Template as it was written.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// MarkdownV2 text with placeholders replaced by plain text `value`s.
+    pub fn render(&self, value: impl Fn(Field) -> String) -> String {
+        let mut text = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => text.push_str(literal),
+                Part::Placeholder(field, context) => {
+                    let value = value(*field);
+                    text.push_str(&match context {
+                        Context::Text => markdown::escape(&value),
+                        Context::Code => markdown::escape_code(&value),
+                        Context::Url => markdown::escape_link_url(&value),
+                    });
+                }
+            }
+        }
+        text
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = TemplateError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str) -> String {
+        Template::parse(source)
+            .unwrap()
+            .render(|field| match field {
+                Field::Link => "https://example.com/a_(b)".to_string(),
+                _ => "v.1 (x)`y`".to_string(),
+            })
+    }
+
+    #[test]
+    fn escaped_text() {
+        assert_eq!(render("\\#1 \\- ok\\!"), "\\#1 \\- ok\\!");
+        assert_eq!(render("{vehicle}"), "v\\.1 \\(x\\)\\`y\\`");
+    }
+
+    #[test]
+    fn reserved_chars() {
+        for (source, c, i) in [
+            ("a.b", '.', 1),
+            ("T-0", '-', 1),
+            ("(x", '(', 0),
+            ("x)", ')', 1),
+            ("> quote", '>', 0),
+            ("||spoiler||", '|', 0),
+            ("a]", ']', 1),
+            ("[a]", ']', 2),
+            ("[a [b](c)](d)", '[', 3),
+            ("}", '}', 0),
+        ] {
+            assert_eq!(
+                Template::parse(source),
+                Err(TemplateError::Unescaped(c, i)),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn entities() {
+        for source in [
+            "*bold* _italic_ __underline__ ~strike~",
+            "*bold _italic_*",
+            "__underline _and italic_ __",
+            "*{provider}* in {countdown}",
+        ] {
+            assert!(Template::parse(source).is_ok(), "{}", source);
+        }
+        assert_eq!(
+            Template::parse("*a _b* c_"),
+            Err(TemplateError::Misnested('*', 5, "bold"))
+        );
+        assert_eq!(
+            Template::parse("[*a](b)*"),
+            Err(TemplateError::Misnested(']', 3, "link"))
+        );
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            render("[{mission}]({link})"),
+            "[v\\.1 \\(x\\)\\`y\\`](https://example.com/a_(b\\))"
+        );
+        assert_eq!(
+            render("[site](https://x.y/{pad})"),
+            "[site](https://x.y/v.1 (x\\)\\`y\\`)"
+        );
+    }
+
+    #[test]
+    fn code_spans() {
+        assert_eq!(render("`T-0 {countdown}!`"), "`T-0 v.1 (x)\\`y\\`!`");
+        assert_eq!(render("```\n> a|b_c\n```"), "```\n> a|b_c\n```");
+    }
+
+    #[test]
+    fn unclosed() {
+        for (source, err) in [
+            ("`code", TemplateError::UnclosedEntity("code")),
+            ("[a](b", TemplateError::UnclosedEntity("link")),
+            ("[a", TemplateError::UnclosedEntity("link")),
+            ("*a", TemplateError::UnclosedEntity("bold")),
+            ("_a", TemplateError::UnclosedEntity("italic")),
+            ("__a", TemplateError::UnclosedEntity("underline")),
+            ("~a", TemplateError::UnclosedEntity("strikethrough")),
+            ("{pad", TemplateError::UnclosedPlaceholder(0)),
+            ("a\\", TemplateError::TrailingBackslash),
+        ] {
+            assert_eq!(Template::parse(source), Err(err), "{}", source);
+        }
+    }
+
+    #[test]
+    fn unknown_placeholder() {
+        assert_eq!(
+            Template::parse("{date}"),
+            Err(TemplateError::UnknownPlaceholder("date".to_string()))
+        );
+    }
+}
@@ -4,6 +4,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, TimestampSeconds};
 
+use crate::db::DbError;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Provider {
     pub id: u64,
@@ -164,8 +166,8 @@ pub enum RLError {
     #[error("json -> {0}")]
     Json(#[from] serde_json::Error),
 
-    #[error("sled -> {0}")]
-    Sled(#[from] sled::Error),
+    #[error("db -> {0}")]
+    Db(#[from] DbError),
 
     #[error("teloxide -> {0}")]
     Teloxide(#[from] teloxide::RequestError),